@@ -4,8 +4,14 @@ pub mod decimals;
 pub mod environment;
 pub mod errors;
 pub mod evaluator;
+pub mod functions;
 pub mod integers;
+pub mod operators;
 pub mod parser;
 pub mod patterns;
+pub mod session;
+pub mod settings;
+pub mod suggestions;
 pub mod tokens;
 pub mod values;
+pub mod warnings;
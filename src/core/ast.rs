@@ -1,8 +1,11 @@
 use std::fmt::Display;
 use std::ops::{Deref, DerefMut};
+use std::str::FromStr;
 
-use crate::core::tokens::Token;
-use crate::core::values::Value;
+use crate::core::integers::Integer;
+use crate::core::patterns;
+use crate::core::tokens::{Token, TokenType};
+use crate::core::values::{Value, ValueType};
 
 pub struct Ast {
     _vec: Vec<AstNode>,
@@ -46,6 +49,36 @@ impl Ast {
         self._vec.len()
     }
 
+    pub fn to_sexpr(&self) -> String {
+        // Top-level statements are separated by spaces, e.g. "(+ 2 (* 3 4)) 5"
+        self._vec
+            .iter()
+            .map(AstNode::to_sexpr)
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    pub fn tokens(&self) -> impl Iterator<Item = &Token> {
+        // Every token in the tree, implicit ones included, in the order of the input
+        let mut tokens = Vec::new();
+        for node in self._vec.iter() {
+            node._collect_tokens(&mut tokens);
+        }
+        tokens.into_iter()
+    }
+
+    pub fn simplify(&mut self) {
+        // Folds operations on literals and drops operations with an identity operand, e.g.
+        // "2 + 3" -> "5" and "(x + 1) * 1" -> "x + 1"; variables and assignments are never
+        // folded. Literals are read in base 10 and operators with their builtin meaning, and
+        // no warnings are given, so the evaluator only simplifies where that is all it takes.
+        for node in self._vec.iter_mut() {
+            node.simplify();
+        }
+        let level = self._level;
+        self.relevel_from(level);
+    }
+
     pub fn relevel_from(&mut self, base_level: usize) {
         self._level = base_level;
         for node in self._vec.iter_mut() {
@@ -81,8 +114,19 @@ impl IntoIterator for Ast {
     type Item = AstNode;
     type IntoIter = std::vec::IntoIter<Self::Item>;
 
-    fn into_iter(self) -> Self::IntoIter {
-        self._vec.into_iter()
+    fn into_iter(mut self) -> Self::IntoIter {
+        std::mem::take(&mut self._vec).into_iter()
+    }
+}
+
+impl Drop for Ast {
+    fn drop(&mut self) {
+        // Unnest the subtrees first, as dropping a long operator chain recursively could
+        // exhaust the stack before the parser gets to reject it
+        let mut pending = std::mem::take(&mut self._vec);
+        while let Some(mut node) = pending.pop() {
+            pending.append(&mut node.subtree._vec);
+        }
     }
 }
 
@@ -108,21 +152,20 @@ impl From<&mut Vec<AstNode>> for Ast {
     }
 }
 
+// The conversions below leave the levels of the nodes' subtrees untouched, so that
+// building a tree bottom-up stays linear; use `relevel_from` once it is complete
 impl From<Vec<AstNode>> for Ast {
     fn from(value: Vec<AstNode>) -> Self {
-        let mut tree = Self::new();
-        for node in value {
-            tree.push(node);
+        Self {
+            _vec: value,
+            _level: 0,
         }
-        tree
     }
 }
 
 impl From<AstNode> for Ast {
     fn from(value: AstNode) -> Self {
-        let mut tree = Self::new();
-        tree.push(value);
-        tree
+        Self::from(vec![value])
     }
 }
 
@@ -149,6 +192,62 @@ impl AstNode {
         }
     }
 
+    pub fn to_sexpr(&self) -> String {
+        // Parentheses are implied by the nesting, so expressions render as their contents
+        if !self.has_children() {
+            return self.token.content_to_string();
+        }
+        if self.token.type_ == TokenType::Expression {
+            return self.subtree[0].to_sexpr();
+        }
+        if self.token.type_ == TokenType::List {
+            let elements: Vec<String> = self.subtree.iter().map(|e| e.to_sexpr()).collect();
+            return format!("[{}]", elements.join(" "));
+        }
+        let mut sexpr = format!("({}", self.token.content_to_string());
+        for child in self.subtree.iter() {
+            sexpr.push(' ');
+            sexpr.push_str(&child.to_sexpr());
+        }
+        sexpr.push(')');
+        sexpr
+    }
+
+    fn _collect_tokens<'a>(&'a self, tokens: &mut Vec<&'a Token>) {
+        // Binary operations sit between their operands, and unary ones before theirs
+        // unless they are postfix, like `!`. A list's token stands for its opening bracket,
+        // so it comes before the elements however many there are
+        if self.token.type_ == TokenType::List {
+            tokens.push(&self.token);
+            for element in self.subtree.iter() {
+                element._collect_tokens(tokens);
+            }
+            return;
+        }
+        match self.subtree.len() {
+            2 => {
+                self.subtree[0]._collect_tokens(tokens);
+                tokens.push(&self.token);
+                self.subtree[1]._collect_tokens(tokens);
+            }
+            1 if self.subtree[0]._is_before(&self.token) => {
+                self.subtree[0]._collect_tokens(tokens);
+                tokens.push(&self.token);
+            }
+            _ => {
+                tokens.push(&self.token);
+                for child in self.subtree.iter() {
+                    child._collect_tokens(tokens);
+                }
+            }
+        }
+    }
+
+    fn _is_before(&self, token: &Token) -> bool {
+        let (a, b) = (&self.token.position, &token.position);
+        (a.line, a.chr) < (b.line, b.chr)
+    }
+
     pub fn has_children(&self) -> bool {
         self.subtree.len() > 0
     }
@@ -160,6 +259,140 @@ impl AstNode {
     pub fn set_subtree(&mut self, subtree: Ast) -> Ast {
         std::mem::replace(&mut self.subtree, subtree)
     }
+
+    pub fn literal_value(&self) -> Option<Value> {
+        if !self.token.type_.is_numeral() {
+            return None;
+        }
+        match &self.value {
+            Some(value) => Some(value.clone()),
+            None => Value::from_str(&self.token.content_to_string()).ok(),
+        }
+    }
+
+    fn _literal_integer(&self) -> Option<Integer> {
+        // Only Integer literals are identity operands, so that folding never changes the type.
+        // The value decides, as `0B101` is tokenized as an Integer but read as a Bitseq.
+        let value = self.literal_value()?;
+        if value.value_type() != ValueType::Integer {
+            return None;
+        }
+        value.try_into().ok()
+    }
+
+    fn _fold_into(&mut self, value: Value) {
+        let type_ = match value.value_type() {
+            ValueType::Bitseq => TokenType::Bitseq,
+            ValueType::Decimal => TokenType::Decimal,
+            ValueType::Integer => TokenType::Integer,
+        };
+        self.token = Token::new_implicit(
+            type_,
+            value.to_numeral_string().chars().collect(),
+            self.token.position.clone(),
+        );
+        self.subtree = Ast::new();
+        self.value = Some(value);
+    }
+
+    fn _fold_binary_operation(&self) -> Option<Value> {
+        let left = self.subtree[0].literal_value()?;
+        let right = self.subtree[1].literal_value()?;
+        // Failing operations are left for the evaluator to report, and so are those that
+        // lose the width of a Bitseq, which the evaluator warns about
+        let result = match self.token.content_to_string().as_str() {
+            "^" => left.pow(&right).ok(),
+            "+" => left.add(&right).ok(),
+            "-" => left.sub(&right).ok(),
+            "*" => left.mul(&right).ok(),
+            "/" => left.div(&right).ok(),
+            "//" => left.floor_div(&right).ok(),
+            "%" => left.rem(&right).ok(),
+            "<<" => left.shl(&right).ok(),
+            ">>" => left.shr(&right).ok(),
+            _ => None,
+        }?;
+        let is_bitseq = |value: &Value| value.value_type() == ValueType::Bitseq;
+        if (is_bitseq(&left) || is_bitseq(&right)) && !is_bitseq(&result) {
+            return None;
+        }
+        Some(result)
+    }
+
+    fn _identity_operand(&self) -> Option<usize> {
+        // Index of the operand that the whole operation reduces to, if any. As arithmetic
+        // turns Bitseqs into Integers, that operand must be known not to be a Bitseq.
+        let is = |i: usize, n: Integer| self.subtree[i]._literal_integer() == Some(n);
+        let operand = match self.token.content_to_string().as_str() {
+            "+" if is(1, Integer::ZERO) => 0,
+            "+" if is(0, Integer::ZERO) => 1,
+            "-" if is(1, Integer::ZERO) => 0,
+            "*" if is(1, Integer::ONE) => 0,
+            "*" if is(0, Integer::ONE) => 1,
+            "^" if is(1, Integer::ONE) => 0,
+            _ => return None,
+        };
+        self.subtree[operand]
+            ._is_arithmetic_result()
+            .then_some(operand)
+    }
+
+    fn _is_arithmetic_result(&self) -> bool {
+        // Whether the node is sure to evaluate to an Integer or a Decimal; variables and
+        // function calls may hold anything
+        if let Some(value) = self.literal_value() {
+            return value.value_type() != ValueType::Bitseq;
+        }
+        let content = self.token.content_to_string();
+        match self.token.type_ {
+            TokenType::BinaryOperator => patterns::ARITHMETIC_OPERATORS.contains(&content.as_str()),
+            // Signs and parentheses keep the type of what they apply to
+            TokenType::UnaryOperator if content != "+" && content != "-" => false,
+            TokenType::UnaryOperator | TokenType::Expression => {
+                self.subtree.len() == 1 && self.subtree[0]._is_arithmetic_result()
+            }
+            _ => false,
+        }
+    }
+
+    pub fn simplify(&mut self) {
+        if patterns::ASSIGNMENT_OPERATORS.contains(&self.token.content_to_string().as_str()) {
+            // The assignee must stay a variable, but the assigned expression can be simplified
+            if let Some(node) = self.subtree.get_mut(1) {
+                node.simplify();
+            }
+            return;
+        }
+        for child in self.subtree.iter_mut() {
+            child.simplify();
+        }
+        match self.token.type_ {
+            TokenType::Expression
+                if self.subtree.len() == 1 && self.subtree[0].literal_value().is_some() =>
+            {
+                *self = self.subtree.remove(0);
+            }
+            TokenType::UnaryOperator if self.subtree.len() == 1 => {
+                let operand = self.subtree[0].literal_value();
+                let folded = match (self.token.content_to_string().as_str(), operand) {
+                    ("+", Some(operand)) => Some(operand.unary_pos()),
+                    ("-", Some(operand)) => Some(operand.unary_neg()),
+                    _ => None,
+                };
+                if let Some(value) = folded {
+                    self._fold_into(value);
+                }
+            }
+            TokenType::BinaryOperator if self.subtree.len() == 2 => {
+                if let Some(value) = self._fold_binary_operation() {
+                    self._fold_into(value);
+                } else if let Some(i) = self._identity_operand() {
+                    *self = self.subtree.remove(i);
+                }
+            }
+            _ => {}
+        }
+    }
 }
 
 impl Display for AstNode {
@@ -177,3 +410,94 @@ impl Display for AstNode {
         write!(f, "")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::evaluator::Evaluator;
+    use crate::core::functions::FunctionRegistry;
+    use crate::core::parser::Parser;
+
+    fn parsed(input: &str) -> Ast {
+        Parser::new()
+            .parse(input, 1, 1, &FunctionRegistry::default())
+            .unwrap()
+    }
+
+    fn simplified(input: &str) -> String {
+        let mut ast = parsed(input);
+        ast.simplify();
+        ast.to_sexpr()
+    }
+
+    #[test]
+    fn identity_operations_are_dropped_only_where_the_type_is_kept() {
+        assert_eq!(simplified("(x + 1) * 1"), simplified("x + 1"));
+        assert_eq!(simplified("2.5 ^ 1 + y"), simplified("2.5 + y"));
+        // x could be a Bitseq, which x + 0 would turn into an Integer
+        assert_eq!(simplified("x + 0"), "(+ x 0)");
+        assert_eq!(simplified("0b101 + 0"), "(+ 0b101 0)");
+        // Tokenized as a Decimal and an Integer, but read as Bitseqs
+        assert_eq!(simplified("0b0.1 + 0"), "(+ 0b0.1 0)");
+        assert_eq!(simplified("0B101 * 1"), "(* 0B101 1)");
+    }
+
+    #[test]
+    fn evaluation_folds_constants_first() {
+        let mut ast = parsed("x * (2 + 3)");
+        let mut evaluator = Evaluator::new();
+        evaluator
+            .environment
+            .variables
+            .set("x", Value::from(Integer::ONE));
+        evaluator.evaluate(&mut ast).unwrap();
+        assert_eq!(ast.to_sexpr(), "(* x 5)");
+    }
+
+    #[test]
+    fn list_tokens_come_before_their_elements() {
+        let ast = parsed("[1, 2]");
+        let tokens: Vec<(TokenType, String)> = ast
+            .tokens()
+            .map(|token| (token.type_, token.content_to_string()))
+            .collect();
+        assert_eq!(
+            tokens,
+            [
+                (TokenType::List, "1, 2".to_string()),
+                (TokenType::Integer, "1".to_string()),
+                (TokenType::Integer, "2".to_string()),
+            ]
+        );
+        // In the order of the input, and the list reads back as the same list
+        let positions: Vec<usize> = ast.tokens().map(|token| token.position.chr).collect();
+        assert!(positions.is_sorted());
+        let source = format!("[{}]", tokens[0].1);
+        assert_eq!(parsed(&source).to_sexpr(), ast.to_sexpr());
+    }
+
+    #[test]
+    fn tokens_include_subtrees_and_implicit_tokens() {
+        let ast = parsed("2 + sin(3 * 4)");
+        let contents: Vec<String> = ast.tokens().map(Token::content_to_string).collect();
+        assert_eq!(contents, ["2", "+", "sin", "3 * 4", "3", "*", "4"]);
+        // A leading operator applies to (mem 0), and 2 sin(...) is an implicit multiplication
+        let ast = parsed("+ 2 sin(3 * 4)");
+        assert_eq!(ast.tokens().count(), 11);
+        let implicit: Vec<String> = ast
+            .tokens()
+            .filter(|token| token.implicit)
+            .map(Token::content_to_string)
+            .collect();
+        assert_eq!(implicit, ["(mem 0)", "mem", "0", "*"]);
+    }
+
+    #[test]
+    fn sexprs_nest_by_precedence() {
+        assert_eq!(parsed("2 + 3 * 4").to_sexpr(), "(+ 2 (* 3 4))");
+        // A leading minus subtracts from (mem 0), after the power is taken
+        assert_eq!(parsed("-2^2").to_sexpr(), "(- (mem 0) (^ 2 2))");
+        assert_eq!(parsed("2 ^ -2").to_sexpr(), "(^ 2 (- 2))");
+        assert_eq!(parsed("sin 3").to_sexpr(), "(sin 3)");
+    }
+}
@@ -11,23 +11,52 @@ pub type BitseqT = u128;
 pub struct Bitseq {
     value: BitseqT,
     len: usize,
+    point: usize,
 }
 
 impl Display for Bitseq {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{:#0len$b}", self.value, len = self.len + 2)
+        // `pad_integral` rather than `pad`, which would cut the digits down to the precision
+        f.pad_integral(true, "", &format!("0b{}", self.to_digit_string()))
     }
 }
 
 impl Bitseq {
-    pub const ZERO: Bitseq = Bitseq { value: 0, len: 1 };
-    pub const ONE: Bitseq = Bitseq { value: 1, len: 1 };
+    pub const ZERO: Bitseq = Bitseq {
+        value: 0,
+        len: 1,
+        point: 0,
+    };
+    pub const ONE: Bitseq = Bitseq {
+        value: 1,
+        len: 1,
+        point: 0,
+    };
+
+    pub fn to_digit_string(self) -> String {
+        // Fixed-point sequences show the binary point `point` bits from the right
+        let digits = format!("{:0len$b}", self.value, len = self.len);
+        if self.point == 0 {
+            return digits;
+        }
+        let (int_digits, frac_digits) = digits.split_at(digits.len() - self.point);
+        let int_digits = if int_digits.is_empty() {
+            "0"
+        } else {
+            int_digits
+        };
+        format!("{}.{}", int_digits, frac_digits)
+    }
 
     pub fn new(value: BitseqT, len: usize) -> Self {
-        if len >= BitseqT::BITS as usize {
+        if len > BitseqT::BITS as usize {
             panic!("Length of Bitseq can be 128 bits at most");
         }
-        Self { value, len }
+        Self {
+            value,
+            len,
+            point: 0,
+        }
     }
 
     pub fn from_str(s: &str) -> Option<Self> {
@@ -45,10 +74,63 @@ impl Bitseq {
             Some(Self {
                 value,
                 len: s.len(),
+                point: 0,
             })
         }
     }
 
+    pub fn from_hex_str(s: &str) -> Option<Self> {
+        // `s` is an unprefixed hexadecimal string, each digit of which (leading zeros
+        // included) is four bits of the sequence
+        let len = s.len() * 4;
+        if s.is_empty() || len > BitseqT::BITS as usize || !s.chars().all(|c| c.is_ascii_hexdigit())
+        {
+            return None;
+        }
+        let value = BitseqT::from_str_radix(s, 16).ok()?;
+        Some(Self {
+            value,
+            len,
+            point: 0,
+        })
+    }
+
+    pub fn from_fixed_point_str(s: &str) -> Option<Self> {
+        // `s` is an unprefixed binary string with a '.' marking the binary point
+        let (int_digits, frac_digits) = s.split_once('.')?;
+        let mut bitseq = Self::from_str(&format!("{int_digits}{frac_digits}"))?;
+        bitseq.point = frac_digits.len();
+        Some(bitseq)
+    }
+
+    pub fn from_parts(value: BitseqT, len: usize, point: usize) -> Option<Self> {
+        // None unless `value` fits into `len` bits and the binary point lies within them
+        if len > BitseqT::BITS as usize
+            || point > len
+            || (len < BitseqT::BITS as usize && value >> len != 0)
+        {
+            return None;
+        }
+        Some(Self { value, len, point })
+    }
+
+    pub fn bit_length(&self) -> usize {
+        self.len
+    }
+
+    pub fn fraction_bits(&self) -> usize {
+        self.point
+    }
+
+    pub fn integer_part(&self) -> BitseqT {
+        self.value >> self.point
+    }
+
+    pub fn has_fractional_part(&self) -> bool {
+        // Shifting the integer bits out leaves the fraction bits, which may be all 128 of them
+        self.point > 0 && self.value << (BitseqT::BITS as usize - self.point) != 0
+    }
+
     pub fn is_zero(&self) -> bool {
         self.value == 0
     }
@@ -64,6 +146,91 @@ impl Bitseq {
     pub fn inner_value(self) -> BitseqT {
         self.value
     }
+
+    pub fn concat(self, other: Self) -> Result<Self, ConversionError> {
+        // Bits appended after a binary point would have no place relative to it
+        if self.point > 0 {
+            return Err(ConversionError::new(format!(
+                "Cannot append bits to the fixed-point Bitseq {}",
+                self
+            )));
+        }
+        let len = self.len + other.len;
+        if len > BitseqT::BITS as usize {
+            return Err(ConversionError::new(format!(
+                "Concatenated Bitseq would be {} bits long, but can be {} bits at most",
+                len,
+                BitseqT::BITS
+            )));
+        }
+        // Only a zero-length sequence is shifted by all 128 bits, and its value is 0
+        let value = self.value.checked_shl(other.len as u32).unwrap_or(0) | other.value;
+        // The binary point of the right-hand sequence stays in place
+        Ok(Self {
+            value,
+            len,
+            point: other.point,
+        })
+    }
+
+    pub fn checked_shl(&self, n: usize) -> Option<Self> {
+        // None if the shifted sequence would no longer fit the backing type
+        let len = self.len.checked_add(n)?;
+        if len > BitseqT::BITS as usize {
+            return None;
+        }
+        // Only a zero-length sequence is shifted by all 128 bits, and its value is 0
+        let value = self.value.checked_shl(n as u32).unwrap_or(0);
+        Some(Self {
+            value,
+            len,
+            point: self.point,
+        })
+    }
+
+    pub fn shr(&self, n: usize) -> Self {
+        // Bits shifted out on the right are dropped, but at least one bit remains
+        let value = if n >= BitseqT::BITS as usize {
+            0
+        } else {
+            self.value >> n
+        };
+        Self {
+            value,
+            len: self.len.saturating_sub(n).max(1),
+            point: 0,
+        }
+    }
+
+    pub fn popcount(&self) -> u32 {
+        self.value.count_ones()
+    }
+
+    pub fn leading_zeros(&self) -> u32 {
+        // Measured against the declared length rather than the backing type
+        self.value.leading_zeros() - (BitseqT::BITS - self.len as u32)
+    }
+
+    pub fn trailing_zeros(&self) -> u32 {
+        self.value.trailing_zeros().min(self.len as u32)
+    }
+
+    pub fn get_bit(&self, n: usize) -> Option<bool> {
+        // Bit 0 is the least significant bit
+        if n >= self.len {
+            return None;
+        }
+        Some((self.value >> n) & 1 == 1)
+    }
+
+    pub fn as_signed_integer(&self) -> Integer {
+        // Interpret as two's complement with the bit at `len - 1` as the sign bit
+        if self.len == 0 {
+            return Integer::ZERO;
+        }
+        let shift = BitseqT::BITS as usize - self.len;
+        Integer::from(((self.value << shift) as i128) >> shift)
+    }
 }
 
 impl Into<BitseqT> for Bitseq {
@@ -77,6 +244,7 @@ impl From<BitseqT> for Bitseq {
         Self {
             value,
             len: (BitseqT::BITS - value.leading_zeros()) as usize,
+            point: 0,
         }
     }
 }
@@ -125,3 +293,61 @@ impl Neg for Bitseq {
         -Integer::from(self.value)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bits(digits: &str) -> Bitseq {
+        Bitseq::from_str(digits).unwrap()
+    }
+
+    #[test]
+    fn signed_reads_the_top_bit_as_the_sign() {
+        assert_eq!(bits("1000").as_signed_integer(), Integer::from(-8i128));
+        assert_eq!(bits("0111").as_signed_integer(), Integer::from(7u32));
+        assert_eq!(bits("1111").as_signed_integer(), Integer::from(-1i128));
+        // The same bits read differently at another width
+        assert_eq!(bits("01111").as_signed_integer(), Integer::from(15u32));
+    }
+
+    #[test]
+    fn concatenation_appends_the_bits_and_adds_the_widths() {
+        let joined = bits("10").concat(bits("011")).unwrap();
+        assert_eq!(joined.to_string(), "0b10011");
+        assert_eq!(joined.bit_length(), 5);
+        assert_eq!(bits("0").concat(bits("00")).unwrap().to_string(), "0b000");
+        let wide = Bitseq::new(0, 100);
+        assert!(wide.concat(Bitseq::new(0, 28)).is_ok());
+        assert!(wide.concat(Bitseq::new(0, 29)).is_err());
+    }
+
+    #[test]
+    fn bits_are_indexed_from_the_least_significant() {
+        let register = bits("1010");
+        let read: Vec<Option<bool>> = (0..5).map(|n| register.get_bit(n)).collect();
+        assert_eq!(
+            read,
+            [Some(false), Some(true), Some(false), Some(true), None]
+        );
+    }
+
+    #[test]
+    fn popcount_counts_the_set_bits() {
+        assert_eq!(bits("1011").popcount(), 3);
+        assert_eq!(bits("0").popcount(), 0);
+        // Integers count the bits of their magnitude
+        assert_eq!(Integer::from(-5i128).popcount(), 2);
+    }
+
+    #[test]
+    fn zero_counts_depend_on_the_width() {
+        assert_eq!(bits("00000100").leading_zeros(), 5);
+        assert_eq!(bits("00000100").trailing_zeros(), 2);
+        assert_eq!(bits("0100").leading_zeros(), 1);
+        assert_eq!(bits("0100").trailing_zeros(), 2);
+        // An all-zero sequence is zeros for its whole width, either way
+        assert_eq!(bits("0000").leading_zeros(), 4);
+        assert_eq!(bits("0000").trailing_zeros(), 4);
+    }
+}
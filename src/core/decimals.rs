@@ -1,7 +1,10 @@
+use std::cell::RefCell;
 use std::cmp::{Eq, Ord, Ordering, PartialEq, PartialOrd};
 use std::fmt::Display;
-use std::ops::{Add, Neg};
+use std::ops::{Add, Div, Mul, Neg, Sub};
 use std::str::FromStr;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
 
 use fastnum::decimal::{Context, ParseError};
 use fastnum::{D512, I512};
@@ -14,6 +17,55 @@ pub const DECIMAL_CONTEXT: Context = Context::default();
 
 pub type DecimalT = D512;
 
+#[derive(Clone, Debug)]
+pub struct StepBudget {
+    remaining: Arc<AtomicUsize>,
+}
+
+impl StepBudget {
+    pub fn new(steps: usize) -> Self {
+        Self {
+            remaining: Arc::new(AtomicUsize::new(steps)),
+        }
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.remaining.load(AtomicOrdering::Relaxed)
+    }
+
+    fn spend(&self) -> Result<(), InvalidOperationError> {
+        match self
+            .remaining
+            .fetch_update(AtomicOrdering::Relaxed, AtomicOrdering::Relaxed, |n| {
+                n.checked_sub(1)
+            }) {
+            Ok(_) => Ok(()),
+            Err(_) => Err(InvalidOperationError::new("Computation budget exceeded")),
+        }
+    }
+}
+
+thread_local! {
+    // Bounds the iterations of the series and reduction loops run on this thread; clones
+    // installed on other threads draw on the same count
+    static STEP_BUDGET: RefCell<Option<StepBudget>> = const { RefCell::new(None) };
+}
+
+pub fn replace_step_budget(budget: Option<StepBudget>) -> Option<StepBudget> {
+    STEP_BUDGET.replace(budget)
+}
+
+pub fn current_step_budget() -> Option<StepBudget> {
+    STEP_BUDGET.with_borrow(Clone::clone)
+}
+
+pub fn spend_step() -> Result<(), InvalidOperationError> {
+    STEP_BUDGET.with_borrow(|budget| match budget {
+        Some(budget) => budget.spend(),
+        None => Ok(()),
+    })
+}
+
 #[derive(Clone, Copy, Debug)]
 pub struct Decimal {
     value: DecimalT,
@@ -26,6 +78,8 @@ impl Decimal {
     pub const ONE: Self = Self {
         value: DecimalT::ONE.with_ctx(DECIMAL_CONTEXT),
     };
+    // fastnum's PI, TAU and E are correctly rounded to all 154 significant digits of D512,
+    // so they need no higher-precision replacement at DECIMAL_CONTEXT
     pub const PI: Self = Self {
         value: DecimalT::PI.with_ctx(DECIMAL_CONTEXT),
     };
@@ -39,19 +93,58 @@ impl Decimal {
         value: DecimalT::from_i32(9_313).with_ctx(DECIMAL_CONTEXT),
     };
 
+    const MAX_EXP: Self = Self {
+        // Just below ln(DecimalT::MAX), which is about 75_805.7
+        value: DecimalT::from_i32(75_800).with_ctx(DECIMAL_CONTEXT),
+    };
+    const MAX_ITERATIONS: usize = 10_000;
+    const GAMMA_DIGITS: u32 = 90;
+    // The Bernoulli numbers B(2) to B(34) as numerator and denominator
+    const BERNOULLI: [(i64, i64); 17] = [
+        (1, 6),
+        (-1, 30),
+        (1, 42),
+        (-1, 30),
+        (5, 66),
+        (-691, 2_730),
+        (7, 6),
+        (-3_617, 510),
+        (43_867, 798),
+        (-174_611, 330),
+        (854_513, 138),
+        (-236_364_091, 2_730),
+        (8_553_103, 6),
+        (-23_749_461_029, 870),
+        (8_615_841_276_005, 14_322),
+        (-7_709_321_041_217, 510),
+        (2_577_687_858_367, 6),
+    ];
+    // Significant digits within which a result is taken to be exact, leaving the last few of
+    // the context's 154 to the rounding errors of the series
+    const EXACT_RESULT_DIGITS: u32 = 150;
+    const SNAP_DIGITS: usize = 50;
+
     pub fn inner_value(self) -> DecimalT {
         self.value
     }
 
     pub fn gamma(self) -> Result<Self, InvalidOperationError> {
-        // Uses Nemes' improved transformation of the Stirling-De Moivre Approximation.
-        // See Nemes, G. (2010) New asymptotic expansion for the Gamma function,
-        // Archiv der Mathematik, 95: 161-169. doi:10.1007/s00013-010-0146-9
-        // gamma(x) = ((1/e) * (x + (1 / ((12 * x) - (1/(10 * x))))))^x * (sqrt((2*pi)/x))
+        // Only the approximations are rounded to GAMMA_DIGITS, so that the digits shown are
+        // all correct while factorials stay exact
+        let value = self._gamma()?;
+        if self.value == self.value.trunc() {
+            Ok(value)
+        } else {
+            Ok(value.round_significant(Self::GAMMA_DIGITS))
+        }
+    }
+
+    fn _gamma(self) -> Result<Self, InvalidOperationError> {
+        // Positive integers are exact factorials. Other arguments are shifted up by the
+        // recurrence gamma(x + 1) = x * gamma(x) until the Stirling series for ln(gamma(x))
+        // is accurate to about 95 digits
         if self <= Self::ZERO {
-            return Err(InvalidOperationError::new(
-                "Gamma undefined for values <= 0.0",
-            ));
+            return self.gamma_by_reflection();
         }
         if self > Self::MAX_GAMMA {
             return Err(InvalidOperationError::new(format!(
@@ -59,56 +152,620 @@ impl Decimal {
                 Self::MAX_GAMMA
             )));
         }
-        const TWELVE: DecimalT = DecimalT::from_i32(12).with_ctx(DECIMAL_CONTEXT);
-        const RECIP_TEN: DecimalT = DecimalT::ONE
-            .div(DecimalT::from_i32(10))
-            .with_ctx(DECIMAL_CONTEXT);
-        let mut approx = (self.value / DecimalT::E).pow(self.value);
-        approx *= (DecimalT::TAU / self.value).sqrt();
-        approx *= (DecimalT::ONE + (DecimalT::ONE / (TWELVE * self.value.powi(2) - RECIP_TEN)))
-            .pow(self.value);
-        Ok(Self { value: approx })
+        if self.value == self.value.trunc() {
+            return Self::_product(DecimalT::ONE, self.value - DecimalT::ONE);
+        }
+        const STIRLING_MIN: DecimalT = DecimalT::from_i32(1_000).with_ctx(DECIMAL_CONTEXT);
+        const HALF: DecimalT = DecimalT::ONE.div(DecimalT::TWO).with_ctx(DECIMAL_CONTEXT);
+        let mut shifted = self.value;
+        let mut divisor = DecimalT::ONE;
+        while shifted < STIRLING_MIN {
+            spend_step()?;
+            divisor *= shifted;
+            shifted += DecimalT::ONE;
+        }
+        // ln(gamma(z)) = (z - 1/2) ln(z) - z + ln(2 pi) / 2 + sum B(2k) / (2k (2k - 1) z^(2k - 1))
+        let mut series = (shifted - HALF) * Self { value: shifted }.ln()?.value - shifted
+            + Self::TAU.ln()?.value / DecimalT::TWO;
+        let recip_squared = DecimalT::ONE / (shifted * shifted);
+        let mut power = DecimalT::ONE / shifted;
+        for (k, (numerator, denominator)) in Self::BERNOULLI.iter().enumerate() {
+            spend_step()?;
+            let order = DecimalT::from_i64(2 * k as i64 + 2);
+            series += DecimalT::from_i64(*numerator) * power
+                / (DecimalT::from_i64(*denominator) * order * (order - DecimalT::ONE));
+            power *= recip_squared;
+        }
+        Ok(Self {
+            value: Self { value: series }.exp()?.value / divisor,
+        })
+    }
+
+    fn _product(first: DecimalT, last: DecimalT) -> Result<Self, InvalidOperationError> {
+        // first * (first + 1) * ... * last, which is exact as long as it fits the context
+        let mut product = DecimalT::ONE;
+        let mut factor = first;
+        while factor <= last {
+            spend_step()?;
+            product *= factor;
+            factor += DecimalT::ONE;
+        }
+        Ok(Self { value: product })
+    }
+
+    pub fn sqrt(self) -> Result<Self, InvalidOperationError> {
+        // Newton-Raphson on f(y) = y^2 - x, seeded from above so that the iterates
+        // decrease monotonically until they stop changing at the context precision
+        if self < Self::ZERO {
+            return Err(InvalidOperationError::new(
+                "Square root undefined for values < 0.0",
+            ));
+        }
+        if self == Self::ZERO {
+            return Ok(Self::ZERO);
+        }
+        const TWO: DecimalT = DecimalT::TWO.with_ctx(DECIMAL_CONTEXT);
+        let mut root = if self.value > DecimalT::ONE {
+            self.value
+        } else {
+            DecimalT::ONE
+        };
+        for _ in 0..Self::MAX_ITERATIONS {
+            spend_step()?;
+            let next = (root + self.value / root) / TWO;
+            if next >= root {
+                break;
+            }
+            root = next;
+        }
+        // Perfect squares should come out exact rather than off in the last digit
+        let rounded = Self { value: root }
+            .round_significant(Self::EXACT_RESULT_DIGITS)
+            .value;
+        if rounded * rounded == self.value {
+            root = rounded;
+        }
+        Ok(Self { value: root })
+    }
+
+    pub fn ln(self) -> Result<Self, InvalidOperationError> {
+        // The argument is reduced to x = y * e^k with 1 <= y < e, and ln(y) is summed as
+        // 2 * atanh((y - 1) / (y + 1)). Each reduction step costs up to one rounding in the
+        // last digit, so results are accurate to within a few units in the last place of
+        // DECIMAL_CONTEXT unless the argument is astronomically far from 1.
+        if self <= Self::ZERO {
+            return Err(InvalidOperationError::new(
+                "Natural logarithm undefined for values <= 0.0",
+            ));
+        }
+        let e = Self::E.value;
+        let mut reduced = self.value;
+        let mut k: i64 = 0;
+        while reduced >= e {
+            spend_step()?;
+            reduced /= e;
+            k += 1;
+        }
+        while reduced < DecimalT::ONE {
+            spend_step()?;
+            reduced *= e;
+            k -= 1;
+        }
+        let z = (reduced - DecimalT::ONE) / (reduced + DecimalT::ONE);
+        let z_squared = z * z;
+        let mut power = z;
+        let mut sum = DecimalT::ZERO;
+        let mut n = DecimalT::ONE;
+        for _ in 0..Self::MAX_ITERATIONS {
+            spend_step()?;
+            let next = sum + power / n;
+            if next == sum {
+                break;
+            }
+            sum = next;
+            power *= z_squared;
+            n += DecimalT::TWO;
+        }
+        Ok(Self {
+            value: DecimalT::TWO * sum + DecimalT::from_i64(k),
+        })
+    }
+
+    pub fn log(self, base: Self) -> Result<Self, InvalidOperationError> {
+        if base <= Self::ZERO || base == Self::ONE {
+            return Err(InvalidOperationError::new(format!(
+                "Logarithm undefined for base {}",
+                base
+            )));
+        }
+        let ratio = self.ln()?.value / base.ln()?.value;
+        Ok(Self {
+            value: Self::snap_near_exact(ratio),
+        })
+    }
+
+    pub fn log2(self) -> Result<Self, InvalidOperationError> {
+        self.log(Self::from(2u128))
+    }
+
+    pub fn log10(self) -> Result<Self, InvalidOperationError> {
+        self.log(Self::from(10u128))
+    }
+
+    pub fn asinh(self) -> Result<Self, InvalidOperationError> {
+        // asinh(x) = ln(x + sqrt(x^2 + 1)), taken on |x| since the function is odd and the
+        // sum would otherwise cancel for large negative x
+        let magnitude = self.abs();
+        let radicand = Self {
+            value: magnitude.value * magnitude.value + DecimalT::ONE,
+        };
+        let result = (magnitude + radicand.sqrt()?).ln()?;
+        Ok(if self < Self::ZERO { -result } else { result })
+    }
+
+    pub fn acosh(self) -> Result<Self, InvalidOperationError> {
+        // acosh(x) = ln(x + sqrt(x^2 - 1))
+        if self < Self::ONE {
+            return Err(InvalidOperationError::new(
+                "Inverse hyperbolic cosine undefined for values < 1.0",
+            ));
+        }
+        let radicand = Self {
+            value: self.value * self.value - DecimalT::ONE,
+        };
+        (self + radicand.sqrt()?).ln()
+    }
+
+    pub fn atanh(self) -> Result<Self, InvalidOperationError> {
+        // atanh(x) = ln((1 + x) / (1 - x)) / 2
+        if self.abs() >= Self::ONE {
+            return Err(InvalidOperationError::new(
+                "Inverse hyperbolic tangent undefined for values outside (-1, 1)",
+            ));
+        }
+        let ratio = Self {
+            value: (DecimalT::ONE + self.value) / (DecimalT::ONE - self.value),
+        };
+        Ok(Self {
+            value: ratio.ln()?.value / DecimalT::TWO,
+        })
+    }
+
+    pub fn exp(self) -> Result<Self, InvalidOperationError> {
+        // exp(x) = exp(x / 2^k)^(2^k): the argument is halved until |x| <= 1/2, the Taylor
+        // series is summed for the reduced argument, and the result squared back up
+        if self > Self::MAX_EXP {
+            return Err(InvalidOperationError::new(format!(
+                "Exponential of value > {} exceeds size of Decimal type",
+                Self::MAX_EXP
+            )));
+        }
+        if self < -Self::MAX_EXP {
+            return Ok(Self::ZERO);
+        }
+        const HALF: DecimalT = DecimalT::ONE.div(DecimalT::TWO).with_ctx(DECIMAL_CONTEXT);
+        let mut reduced = self.value;
+        let mut squarings: usize = 0;
+        while reduced.abs() > HALF {
+            spend_step()?;
+            reduced /= DecimalT::TWO;
+            squarings += 1;
+        }
+        let mut term = DecimalT::ONE;
+        let mut sum = DecimalT::ONE;
+        let mut n = DecimalT::ONE;
+        for _ in 0..Self::MAX_ITERATIONS {
+            spend_step()?;
+            term = term * reduced / n;
+            let next = sum + term;
+            if next == sum {
+                break;
+            }
+            sum = next;
+            n += DecimalT::ONE;
+        }
+        for _ in 0..squarings {
+            spend_step()?;
+            sum *= sum;
+        }
+        if !sum.is_finite() {
+            return Err(InvalidOperationError::new(format!(
+                "Exponential of {} is not a finite Decimal",
+                self
+            )));
+        }
+        Ok(Self { value: sum })
+    }
+
+    pub fn pow(self, exponent: Self) -> Result<Self, InvalidOperationError> {
+        // Integral exponents are computed exactly by repeated squaring, anything else as
+        // exp(exponent * ln(base))
+        let integral_exponent = Integer::try_from(exponent)
+            .ok()
+            .and_then(|i| i.inner_value().to_i64().ok());
+        if self == Self::ZERO {
+            return if exponent == Self::ZERO {
+                Ok(Self::ONE)
+            } else if exponent > Self::ZERO {
+                Ok(Self::ZERO)
+            } else {
+                Err(InvalidOperationError::new(
+                    "Zero raised to a negative power is undefined",
+                ))
+            };
+        }
+        if self < Self::ZERO && integral_exponent.is_none() {
+            return Err(InvalidOperationError::new(
+                "Negative values raised to a fractional power are undefined",
+            ));
+        }
+        let log_magnitude = exponent.value * self.abs().ln()?.value;
+        if log_magnitude > Self::MAX_EXP.value {
+            return Err(InvalidOperationError::new(format!(
+                "{} raised to the power of {} exceeds size of Decimal type",
+                self, exponent
+            )));
+        }
+        if log_magnitude < -Self::MAX_EXP.value {
+            return Ok(Self::ZERO);
+        }
+        let Some(n) = integral_exponent else {
+            let result = Self {
+                value: log_magnitude,
+            }
+            .exp()?;
+            return Ok(Self {
+                value: Self::snap_near_exact(result.value),
+            });
+        };
+        let mut base = self.value;
+        let mut remaining = n.unsigned_abs();
+        let mut result = DecimalT::ONE;
+        while remaining > 0 {
+            spend_step()?;
+            if remaining & 1 == 1 {
+                result *= base;
+            }
+            remaining >>= 1;
+            if remaining > 0 {
+                base *= base;
+            }
+        }
+        if n < 0 {
+            result = DecimalT::ONE / result;
+        }
+        Ok(Self { value: result })
+    }
+
+    fn snap_near_exact(value: DecimalT) -> DecimalT {
+        // Results like 9^0.5 land a few units in the last place away from an exact value,
+        // in which case the short exact value is preferred. The rounding is to significant
+        // digits, so that tiny results are not mistaken for 0.
+        let rounded = Self { value }
+            .round_significant(Self::EXACT_RESULT_DIGITS)
+            .value
+            .reduce();
+        if rounded.digits_count() <= Self::SNAP_DIGITS {
+            rounded
+        } else {
+            value
+        }
+    }
+
+    pub fn beta(self, other: Self) -> Result<Self, InvalidOperationError> {
+        // B(x, y) = gamma(x) * gamma(y) / gamma(x + y), which for positive integers
+        // m <= n is exactly (m - 1)! / (n * (n + 1) * ... * (m + n - 1))
+        let is_count = |x: Self| x > Self::ZERO && x.value == x.value.trunc();
+        if is_count(self) && is_count(other) {
+            let (m, n) = if self <= other {
+                (self.value, other.value)
+            } else {
+                (other.value, self.value)
+            };
+            let numerator = Self::_product(DecimalT::ONE, m - DecimalT::ONE)?;
+            let denominator = Self::_product(n, m + n - DecimalT::ONE)?;
+            return Ok(numerator / denominator);
+        }
+        let numerator = self._gamma()?.value * other._gamma()?.value;
+        Ok(Self {
+            value: numerator / (self + other)._gamma()?.value,
+        }
+        .round_significant(Self::GAMMA_DIGITS))
+    }
+
+    pub fn hypot(self, other: Self) -> Result<Self, InvalidOperationError> {
+        // Both sides are scaled by the power of ten of the larger magnitude, which is exact
+        // in decimal, so that squaring neither overflows nor underflows
+        let (x, y) = (self.abs().value, other.abs().value);
+        let larger = if x >= y { x } else { y };
+        if larger == DecimalT::ZERO {
+            return Ok(Self::ZERO);
+        }
+        let magnitude = larger.digits_count() as i32 - larger.fractional_digits_count() as i32 - 1;
+        let scale = DecimalT::quantum(magnitude, DECIMAL_CONTEXT);
+        let (x, y) = (x / scale, y / scale);
+        let root = Self {
+            value: x * x + y * y,
+        }
+        .sqrt()?;
+        Ok(Self {
+            value: (root.value * scale).reduce(),
+        })
+    }
+
+    fn gamma_by_reflection(self) -> Result<Self, InvalidOperationError> {
+        // Euler's reflection formula gamma(x) * gamma(1 - x) = pi / sin(pi * x) extends gamma
+        // to negative non-integers, whereas the non-positive integers are poles
+        if self.value == self.value.trunc() {
+            return Err(InvalidOperationError::new(format!(
+                "Gamma undefined for non-positive integer {}",
+                self
+            )));
+        }
+        let sin = Self {
+            value: DecimalT::PI * self.value,
+        }
+        .sin(AngleUnit::Radians);
+        let complement = (Self::ONE - self)._gamma()?;
+        Ok(Self {
+            value: DecimalT::PI / (sin.value * complement.value),
+        })
+    }
+
+    pub fn floor(self) -> Self {
+        Self {
+            value: self.value.floor(),
+        }
+    }
+
+    pub fn round(self) -> Self {
+        // To the nearest whole number, halves away from zero
+        Self {
+            value: self.value.round(0),
+        }
+    }
+
+    pub fn round_significant(self, digits: u32) -> Self {
+        // To `digits` significant digits, halves away from zero
+        let excess = self.value.digits_count() as i64 - digits as i64;
+        if excess <= 0 {
+            return self;
+        }
+        // Results of fastnum's series carry a context that rounds down, so the rounding
+        // mode is set here
+        let fractional_digits = self.value.fractional_digits_count() as i64 - excess;
+        Self {
+            value: self
+                .value
+                .with_ctx(DECIMAL_CONTEXT)
+                .round(fractional_digits as i16),
+        }
+    }
+
+    fn _normalize_mantissa(
+        integral: &str,
+        fractional: &str,
+        exponent: &str,
+    ) -> (String, String, String) {
+        // Moves the point behind the first nonzero digit, so that "602.0e+21" reads "6.02e+23"
+        let digits = format!("{integral}{fractional}");
+        let exponent: i64 = exponent.parse().unwrap_or(0);
+        let Some(first) = digits.find(|c: char| c != '0') else {
+            return ("0".to_string(), String::new(), String::new());
+        };
+        let exponent = exponent + integral.len() as i64 - 1 - first as i64;
+        (
+            digits[first..first + 1].to_string(),
+            digits[first + 1..].to_string(),
+            format!("e{exponent:+}"),
+        )
+    }
+
+    pub fn fract(self) -> Self {
+        // Takes the sign of `self`, so that x == trunc(x) + fract(x)
+        Self {
+            value: self.value - self.value.trunc(),
+        }
     }
 
     pub fn abs(&self) -> Self {
         return Self { value: self.value.abs() }
     }
 
-    fn reduce_radians(radians: DecimalT) -> DecimalT {
-        let mut radians = radians;
-        while radians >= DecimalT::TAU {
-            radians -= DecimalT::TAU;
+    fn reduce_modulo(value: DecimalT, period: DecimalT) -> DecimalT {
+        // Into [0, period) in either direction, without stepping through every period; by
+        // floor division like `Value::rem`, as fastnum's `%` can leave more than a period behind
+        let reduced = value - period * (value / period).floor();
+        if reduced < DecimalT::ZERO {
+            reduced + period
+        } else if reduced >= period {
+            reduced - period
+        } else {
+            reduced
+        }
+    }
+
+    pub fn to_radians_in_unit(self, unit: AngleUnit) -> Self {
+        match unit {
+            AngleUnit::Radians => self,
+            AngleUnit::Degrees => Self {
+                value: self.value.to_radians(),
+            },
+        }
+    }
+
+    pub fn from_radians_in_unit(radians: Self, unit: AngleUnit) -> Self {
+        // The counterpart of `to_radians_in_unit`, taking the angle as an argument rather than
+        // as `self` as is usual for `from_` constructors
+        match unit {
+            AngleUnit::Radians => radians,
+            AngleUnit::Degrees => Self {
+                value: Self::snap_near_exact(radians.value.to_degrees()),
+            },
         }
-        radians
     }
 
-    fn prep_trig_value(value: DecimalT, mode: AngleUnit) -> DecimalT {
-        match mode {
-            AngleUnit::Radians => Self::reduce_radians(value),
-            AngleUnit::Degrees => Self::reduce_radians(value.to_radians()),
+    fn prep_trig_value(self, unit: AngleUnit) -> DecimalT {
+        // Degrees are reduced before the conversion, where a multiple of 360 is still exact
+        const FULL_CIRCLE: DecimalT = DecimalT::from_i32(360).with_ctx(DECIMAL_CONTEXT);
+        let angle = match unit {
+            AngleUnit::Radians => self,
+            AngleUnit::Degrees => Self {
+                value: Self::reduce_modulo(self.value, FULL_CIRCLE),
+            },
+        };
+        Self::reduce_modulo(angle.to_radians_in_unit(unit).value, DecimalT::TAU)
+    }
+
+    fn snap_trig_result(angle: DecimalT, result: DecimalT) -> DecimalT {
+        // A result within the rounding error of the angle is 0, which makes e.g. sin(180) in
+        // degrees come out as exactly 0 while sin(1e-120) keeps its value
+        let noise =
+            angle.abs() * DecimalT::quantum(-(Self::EXACT_RESULT_DIGITS as i32), DECIMAL_CONTEXT);
+        if result.abs() <= noise {
+            DecimalT::ZERO
+        } else {
+            Self::snap_near_exact(result)
         }
     }
 
-    pub fn sin(&self, mode: AngleUnit) -> Self {
-        return Self { value: Self::prep_trig_value(self.value, mode).sin() }
+    pub fn sin(&self, unit: AngleUnit) -> Self {
+        let angle = self.prep_trig_value(unit);
+        Self {
+            value: Self::snap_trig_result(angle, angle.sin()),
+        }
+    }
+
+    pub fn cos(&self, unit: AngleUnit) -> Self {
+        let angle = self.prep_trig_value(unit);
+        Self {
+            value: Self::snap_trig_result(angle, angle.cos()),
+        }
+    }
+
+    pub fn tan(&self, unit: AngleUnit) -> Result<Self, InvalidOperationError> {
+        let cos = self.cos(unit);
+        if cos == Self::ZERO {
+            return Err(InvalidOperationError::new(format!(
+                "Tangent undefined for {}",
+                self
+            )));
+        }
+        Ok(Self {
+            value: Self::snap_near_exact(self.sin(unit).value / cos.value),
+        })
+    }
+
+    pub fn cot(&self, unit: AngleUnit) -> Result<Self, InvalidOperationError> {
+        let sin = self.sin(unit);
+        if sin == Self::ZERO {
+            return Err(InvalidOperationError::new(format!(
+                "Cotangent undefined for {}",
+                self
+            )));
+        }
+        Ok(Self {
+            value: Self::snap_near_exact(self.cos(unit).value / sin.value),
+        })
+    }
+
+    pub fn sec(&self, unit: AngleUnit) -> Result<Self, InvalidOperationError> {
+        let cos = self.cos(unit);
+        if cos == Self::ZERO {
+            return Err(InvalidOperationError::new(format!(
+                "Secant undefined for {}",
+                self
+            )));
+        }
+        Ok(Self {
+            value: DecimalT::ONE / cos.value,
+        })
+    }
+
+    pub fn csc(&self, unit: AngleUnit) -> Result<Self, InvalidOperationError> {
+        let sin = self.sin(unit);
+        if sin == Self::ZERO {
+            return Err(InvalidOperationError::new(format!(
+                "Cosecant undefined for {}",
+                self
+            )));
+        }
+        Ok(Self {
+            value: DecimalT::ONE / sin.value,
+        })
+    }
+
+    pub fn asin(&self, unit: AngleUnit) -> Result<Self, InvalidOperationError> {
+        if self.abs() > Self::ONE {
+            return Err(InvalidOperationError::new(
+                "Arcsine undefined for values outside [-1, 1]",
+            ));
+        }
+        let radians = Self {
+            value: self.value.asin(),
+        };
+        Ok(Self::from_radians_in_unit(radians, unit))
+    }
+
+    pub fn acos(&self, unit: AngleUnit) -> Result<Self, InvalidOperationError> {
+        if self.abs() > Self::ONE {
+            return Err(InvalidOperationError::new(
+                "Arccosine undefined for values outside [-1, 1]",
+            ));
+        }
+        let radians = Self {
+            value: self.value.acos(),
+        };
+        Ok(Self::from_radians_in_unit(radians, unit))
+    }
+
+    pub fn atan(&self, unit: AngleUnit) -> Self {
+        let radians = Self {
+            value: self.value.atan(),
+        };
+        Self::from_radians_in_unit(radians, unit)
     }
 }
 
 impl Display for Decimal {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let s = self.value.to_string();
-        if s.contains(".") {
-            // Trim trailing zeroes on the fractional part
-            let (p1, p2) = s.split_once(".").unwrap();
-            let fractional = p2.trim_end_matches("0");
-            if fractional.len() == 0 {
-                write!(f, "{}.0", p1)
-            } else {
-                write!(f, "{}.{}", p1, fractional)
+        // A precision rounds to that many fractional digits and keeps trailing zeroes up to it,
+        // while width, fill, alignment and the sign flag are honoured through `pad_integral`.
+        // Very large and small values come in scientific notation, where the precision counts
+        // the fractional digits of the mantissa instead
+        let is_scientific = self.value.to_string().contains(['e', 'E']);
+        let value = match f.precision() {
+            Some(precision) if is_scientific => {
+                self.round_significant(precision.saturating_add(1).min(u32::MAX as usize) as u32)
             }
-        } else {
-            write!(f, "{}.0", s)
-        }
+            Some(precision) => Self {
+                value: self.value.round(precision.min(i16::MAX as usize) as i16),
+            },
+            None => *self,
+        };
+        let s = value.value.to_string();
+        let (s, exponent) = match s.find(['e', 'E']) {
+            Some(index) => (&s[..index], Some(&s[index + 1..])),
+            None => (s.as_str(), None),
+        };
+        let (is_nonnegative, s) = match s.strip_prefix('-') {
+            Some(magnitude) => (false, magnitude),
+            None => (true, s),
+        };
+        let (integral, fractional) = s.split_once(".").unwrap_or((s, ""));
+        let (integral, fractional, exponent) = match exponent {
+            Some(exponent) => Self::_normalize_mantissa(integral, fractional, exponent),
+            None => (integral.to_string(), fractional.to_string(), String::new()),
+        };
+        // Trim trailing zeroes on the fractional part
+        let fractional = fractional.trim_end_matches("0");
+        let digits = match f.precision() {
+            Some(0) => format!("{}{}", integral, exponent),
+            Some(precision) => format!("{}.{:0<precision$}{}", integral, fractional, exponent),
+            None if fractional.is_empty() => format!("{}.0{}", integral, exponent),
+            None => format!("{}.{}{}", integral, fractional, exponent),
+        };
+        f.pad_integral(is_nonnegative, "", &digits)
     }
 }
 
@@ -128,7 +785,12 @@ impl From<u128> for Decimal {
 
 impl From<Bitseq> for Decimal {
     fn from(value: Bitseq) -> Self {
-        Self::from(value.inner_value())
+        // Dividing by a power of two is exact, so fixed-point sequences convert losslessly
+        // The scale is raised in DecimalT, as 2^128 does not fit into a u128
+        let scale = Self {
+            value: DecimalT::TWO.powi(value.fraction_bits() as i32),
+        };
+        Self::from(value.inner_value()) / scale
     }
 }
 
@@ -196,7 +858,218 @@ impl Add for Decimal {
     }
 }
 
+impl Sub for Decimal {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        Self {
+            value: self.value - rhs.value,
+        }
+    }
+}
+
+impl Mul for Decimal {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        Self {
+            value: self.value * rhs.value,
+        }
+    }
+}
+
+impl Div for Decimal {
+    type Output = Self;
+
+    fn div(self, rhs: Self) -> Self::Output {
+        Self {
+            value: self.value / rhs.value,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
 pub enum AngleUnit {
+    #[default]
     Degrees,
     Radians,
 }
+
+#[cfg(test)]
+mod tests {
+    use fastnum::decimal::RoundingMode;
+
+    use super::*;
+
+    #[test]
+    fn scientific_notation_has_one_integer_digit() {
+        let avogadro = Decimal::from_str("602.0e21").unwrap();
+        assert_eq!(avogadro.to_string(), "6.02e+23");
+        let small = Decimal::from_str("12345e-40").unwrap();
+        assert_eq!(small.to_string(), "1.2345e-36");
+        assert_eq!(format!("{small:.2}"), "1.23e-36");
+        assert_eq!(format!("{:+.3}", -small), "-1.235e-36");
+    }
+
+    #[test]
+    fn beta_is_exact_for_whole_numbers_and_accurate_otherwise() {
+        let twelfth = Decimal::ONE / Decimal::from(12u128);
+        assert_eq!(
+            Decimal::from(2u128).beta(Decimal::from(3u128)).unwrap(),
+            twelfth
+        );
+        let half = Decimal::from_str("0.5").unwrap();
+        let pi = Decimal::PI.round_significant(Decimal::GAMMA_DIGITS);
+        assert_eq!(half.beta(half).unwrap(), pi);
+    }
+
+    #[test]
+    fn snapping_to_exact_results_keeps_tiny_values() {
+        let nine = Decimal::from(9u128);
+        let half = Decimal::from_str("0.5").unwrap();
+        assert_eq!(nine.pow(half).unwrap(), Decimal::from(3u128));
+        let tiny = Decimal::from_str("1e-300").unwrap();
+        assert_eq!(
+            tiny.pow(half).unwrap(),
+            Decimal::from_str("1e-150").unwrap()
+        );
+    }
+
+    #[test]
+    fn sqrt_is_exact_for_squares_and_accurate_otherwise() {
+        let two = Decimal::from(2u128);
+        let root = two.sqrt().unwrap();
+        assert!(
+            root.to_string()
+                .starts_with("1.41421356237309504880168872420969807856967")
+        );
+        let error = (root * root - two).abs();
+        assert!(error < Decimal::from_str("1e-150").unwrap());
+        assert_eq!(Decimal::ZERO.sqrt().unwrap(), Decimal::ZERO);
+        let square = Decimal::from_str("1e10").unwrap();
+        assert_eq!(square.sqrt().unwrap(), Decimal::from(100_000u128));
+        assert!((-two).sqrt().is_err());
+    }
+
+    #[test]
+    fn ln_of_one_e_and_a_large_argument() {
+        assert_eq!(Decimal::ONE.ln().unwrap(), Decimal::ZERO);
+        let one = Decimal::E.ln().unwrap();
+        assert!((one - Decimal::ONE).abs() < Decimal::from_str("1e-150").unwrap());
+        // 100 ln(10)
+        let large = Decimal::from_str("1e100").unwrap().ln().unwrap();
+        assert!(
+            large
+                .to_string()
+                .starts_with("230.258509299404568401799145468436420760110148862877")
+        );
+        assert!(Decimal::ZERO.ln().is_err());
+        assert!((-Decimal::ONE).ln().is_err());
+    }
+
+    #[test]
+    fn exp_converges_for_large_and_negative_arguments() {
+        assert_eq!(Decimal::ZERO.exp().unwrap(), Decimal::ONE);
+        let e = Decimal::ONE.exp().unwrap();
+        assert!((e - Decimal::E).abs() < Decimal::from_str("1e-150").unwrap());
+        let small = Decimal::from(5u128).neg().exp().unwrap();
+        assert!(small.to_string().starts_with(
+            "0.00673794699908546709663604842314842424884958502735508543030553157268352251560406"
+        ));
+        // Too large for the Decimal type, while the reciprocal just underflows to 0
+        let huge = Decimal::from_str("1e10").unwrap();
+        assert!(huge.exp().is_err());
+        assert_eq!(huge.neg().exp().unwrap(), Decimal::ZERO);
+    }
+
+    #[test]
+    fn pow_takes_fractional_exponents() {
+        let half = Decimal::from_str("0.5").unwrap();
+        let two = Decimal::from(2u128);
+        let error = (two.pow(half).unwrap() - two.sqrt().unwrap()).abs();
+        assert!(error < Decimal::from_str("1e-150").unwrap());
+        assert_eq!(
+            Decimal::from(9u128).pow(half).unwrap(),
+            Decimal::from(3u128)
+        );
+        let third = Decimal::ONE / Decimal::from(3u128);
+        assert_eq!(Decimal::from(8u128).pow(third).unwrap(), two);
+        assert!(two.neg().pow(half).is_err());
+        // Integer exponents are exact
+        assert_eq!(
+            Decimal::from(3u128).pow(Decimal::from(40u128)).unwrap(),
+            Decimal::from(12157665459056928801u128)
+        );
+    }
+
+    #[test]
+    fn log2_and_log10_are_exact_for_powers_of_their_base() {
+        assert_eq!(Decimal::from(8u128).log2().unwrap(), Decimal::from(3u128));
+        assert_eq!(
+            Decimal::from(1000u128).log10().unwrap(),
+            Decimal::from(3u128)
+        );
+        assert_eq!(Decimal::ONE.log2().unwrap(), Decimal::ZERO);
+        assert!(Decimal::ZERO.log10().is_err());
+    }
+
+    #[test]
+    fn inverse_hyperbolic_functions_check_their_domains() {
+        assert_eq!(Decimal::ZERO.atanh().unwrap(), Decimal::ZERO);
+        assert_eq!(Decimal::ONE.acosh().unwrap(), Decimal::ZERO);
+        assert_eq!(Decimal::ZERO.asinh().unwrap(), Decimal::ZERO);
+        let half = Decimal::from_str("0.5").unwrap();
+        assert!(half.acosh().is_err());
+        assert!(Decimal::ONE.atanh().is_err());
+        assert!(Decimal::ONE.neg().atanh().is_err());
+        // asinh(x) = ln(x + sqrt(x^2 + 1))
+        let two = Decimal::from(2u128);
+        let expected = (two + Decimal::from(5u128).sqrt().unwrap()).ln().unwrap();
+        assert!((two.asinh().unwrap() - expected).abs() < Decimal::from_str("1e-150").unwrap());
+    }
+
+    #[test]
+    fn rounding_to_significant_digits_rounds_halves_up_whatever_the_context() {
+        let down = DECIMAL_CONTEXT.with_rounding_mode(RoundingMode::Down);
+        let two_thirds = Decimal {
+            value: Decimal::from_str("0.66666").unwrap().value.with_ctx(down),
+        };
+        assert_eq!(
+            two_thirds.round_significant(1),
+            Decimal::from_str("0.7").unwrap()
+        );
+        let sin = Decimal::from(30u128).sin(AngleUnit::Degrees);
+        assert_eq!(sin, Decimal::from_str("0.5").unwrap());
+    }
+
+    #[test]
+    fn angles_are_reduced_in_either_direction() {
+        let sin = |degrees: &str| Decimal::from_str(degrees).unwrap().sin(AngleUnit::Degrees);
+        assert_eq!(sin("-90"), Decimal::ONE.neg());
+        assert_eq!(sin("720"), Decimal::ZERO);
+        assert_eq!(sin("-450"), Decimal::ONE.neg());
+        assert_eq!(sin("-359970"), Decimal::from_str("0.5").unwrap());
+        // Radians are reduced by multiples of tau, which is not exact
+        let radians = Decimal::from(90u128).sin(AngleUnit::Radians);
+        assert!(radians.to_string().starts_with(
+            "0.89399666360055789051826949840420988004643052366630451235226941513949921379"
+        ));
+    }
+
+    #[test]
+    fn hypot_does_not_overflow_on_large_common_factors() {
+        let hypot = |x: &str, y: &str| {
+            Decimal::from_str(x)
+                .unwrap()
+                .hypot(Decimal::from_str(y).unwrap())
+                .unwrap()
+        };
+        assert_eq!(hypot("3", "4"), Decimal::from(5u128));
+        // Their squares are out of the range of the Decimal type
+        assert_eq!(
+            hypot("3e20000", "4e20000"),
+            Decimal::from_str("5e20000").unwrap()
+        );
+        assert_eq!(hypot("-5", "0"), Decimal::from(5u128));
+    }
+}
@@ -1,16 +1,174 @@
-use crate::core::decimals::Decimal;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use crate::core::decimals::{self, AngleUnit, Decimal};
+use crate::core::errors::InvalidOperationError;
+use crate::core::functions::FunctionRegistry;
+use crate::core::integers::Integer;
+use crate::core::settings::Settings;
 use crate::core::values::{Value, ValueStore};
 
 pub struct Environment {
     pub variables: ValueStore,
+    pub functions: FunctionRegistry,
+    pub angle_unit: AngleUnit,
+    pub settings: Settings,
+    // Results of the previous statements, most recent first, as recalled by `mem n`
+    pub results: VecDeque<Value>,
+}
+
+#[derive(Clone)]
+pub struct EnvironmentSnapshot {
+    variables: HashMap<String, Value>,
+    readonly_variables: HashMap<String, Value>,
+    angle_unit: AngleUnit,
+    settings: Settings,
+}
+
+impl Environment {
+    const RESULTS_LIMIT: usize = 100;
+
+    pub fn remember_result(&mut self, value: Value) {
+        if self.results.len() == Self::RESULTS_LIMIT {
+            self.results.pop_back();
+        }
+        self.results.push_front(value);
+    }
+
+    pub fn recall_result(&self, index: &Value) -> Result<Value, InvalidOperationError> {
+        // `mem 0` is the most recent result, `mem 1` the one before it, and so on
+        if self.results.is_empty() {
+            return Err(InvalidOperationError::new(
+                "There is no previous result to recall yet",
+            ));
+        }
+        let number: Option<Integer> = index.clone().try_into().ok();
+        number
+            .and_then(|number| (0..self.results.len()).find(|n| Integer::from(*n as u32) == number))
+            .map(|n| self.results[n].clone())
+            .ok_or_else(|| {
+                InvalidOperationError::new(format!(
+                    "Only results 0 to {} can be recalled, not {}",
+                    self.results.len() - 1,
+                    index.to_numeral_string()
+                ))
+            })
+    }
+
+    pub fn snapshot(&self) -> EnvironmentSnapshot {
+        // Protected constants survive a restore anyway, so only user entries are kept, along
+        // with the settings, which are assigned to just like variables
+        let mut variables = HashMap::new();
+        let mut readonly_variables = HashMap::new();
+        for (identifier, value) in self.variables.map.iter() {
+            if self.variables.is_protected(identifier) {
+                continue;
+            }
+            if self.variables.is_readonly(identifier) {
+                readonly_variables.insert(identifier.clone(), value.clone());
+            } else {
+                variables.insert(identifier.clone(), value.clone());
+            }
+        }
+        EnvironmentSnapshot {
+            variables,
+            readonly_variables,
+            angle_unit: self.angle_unit,
+            settings: self.settings,
+        }
+    }
+
+    pub fn settings_summary(&self) -> Vec<(String, String)> {
+        // Everything that affects how results are computed and shown, as name/value pairs
+        let mut summary: Vec<(String, String)> = Settings::NAMES
+            .iter()
+            .filter_map(|name| {
+                let value = self.settings.get(name)?;
+                Some((name.to_string(), value.to_numeral_string()))
+            })
+            .collect();
+        summary.push(("angle unit".to_string(), format!("{:?}", self.angle_unit)));
+        summary.push((
+            "rounding mode".to_string(),
+            format!("{:?}", decimals::DECIMAL_CONTEXT.rounding_mode()),
+        ));
+        summary
+    }
+
+    pub fn restore(&mut self, snapshot: EnvironmentSnapshot) {
+        self.variables.clear();
+        for (identifier, value) in snapshot.variables {
+            self.variables.set(identifier, value);
+        }
+        for (identifier, value) in snapshot.readonly_variables {
+            self.variables.set_readonly(identifier, value);
+        }
+        self.angle_unit = snapshot.angle_unit;
+        self.settings = snapshot.settings;
+    }
+}
+
+fn default_variables() -> ValueStore {
+    let mut vs = ValueStore::with_protected_keys(vec!["pi", "π", "tau", "τ", "e"]);
+    vs.set_readonly("pi", Value::from(Decimal::PI));
+    vs.set_readonly("π", Value::from(Decimal::PI));
+    vs.set_readonly("tau", Value::from(Decimal::TAU));
+    vs.set_readonly("τ", Value::from(Decimal::TAU));
+    vs.set_readonly("e", Value::from(Decimal::E));
+    vs
 }
 
 impl Default for Environment {
     fn default() -> Self {
-        let mut vs = ValueStore::with_protected_keys(vec!["pi", "tau", "e"]);
-        vs.set_readonly("pi", Value::from(Decimal::PI));
-        vs.set_readonly("tau", Value::from(Decimal::TAU));
-        vs.set_readonly("e", Value::from(Decimal::E));
-        Self { variables: vs }
+        Self {
+            variables: default_variables(),
+            functions: FunctionRegistry::default(),
+            angle_unit: AngleUnit::default(),
+            settings: Settings::default(),
+            results: VecDeque::new(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct SharedEnvironment {
+    variables: Arc<RwLock<ValueStore>>,
+}
+
+impl SharedEnvironment {
+    pub fn new(variables: ValueStore) -> Self {
+        // Lookups hold the read lock and assignments the write lock only for the single
+        // access, never across an evaluation, so a statement that reads a variable twice
+        // may see another thread's assignment in between
+        Self {
+            variables: Arc::new(RwLock::new(variables)),
+        }
+    }
+
+    pub fn get<S: AsRef<str>>(&self, identifier: S) -> Option<Value> {
+        self.read().get(identifier).cloned()
+    }
+
+    pub fn set<S: AsRef<str>>(&self, identifier: S, value: Value) -> bool {
+        self.write().set(identifier, value)
+    }
+
+    pub fn read(&self) -> RwLockReadGuard<'_, ValueStore> {
+        // A panicking writer cannot leave the store half-updated, so poisoning is ignored
+        self.variables
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn write(&self) -> RwLockWriteGuard<'_, ValueStore> {
+        self.variables
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Default for SharedEnvironment {
+    fn default() -> Self {
+        Self::new(default_variables())
     }
 }
@@ -24,6 +24,8 @@ macro_rules! define_errors {
             pub struct $err_ident {
                 pub msg: String,
                 pub position: InputPosition,
+                pub span: usize,
+                pub source_context: Option<String>,
             }
 
             impl $err_ident {
@@ -33,6 +35,8 @@ macro_rules! define_errors {
                     Self {
                         msg: msg.as_ref().to_string(),
                         position: Default::default(),
+                        span: 1,
+                        source_context: None,
                     }
                 }
 
@@ -40,6 +44,8 @@ macro_rules! define_errors {
                     Self {
                         msg: msg.as_ref().to_string(),
                         position,
+                        span: 1,
+                        source_context: None,
                     }
                 }
 
@@ -49,6 +55,33 @@ macro_rules! define_errors {
                         ..self
                     }
                 }
+
+                pub fn with_span(self, span: usize) -> Self {
+                    Self {
+                        span,
+                        ..self
+                    }
+                }
+
+                pub fn with_source_context<S: AsRef<str>>(self, line: S) -> Self {
+                    // `line` is the text of the line the error occurred on, not the whole input
+                    Self {
+                        source_context: Some(line.as_ref().to_string()),
+                        ..self
+                    }
+                }
+
+                pub fn render<S: AsRef<str>>(&self, source: S) -> String {
+                    let line = source.as_ref().lines().nth(self.position.line).unwrap_or_default();
+                    render_with_carets(self, line, &self.position, self.span)
+                }
+
+                pub fn render_context(&self) -> Option<String> {
+                    // Like `render`, but using the line attached with `with_source_context`
+                    self.source_context
+                        .as_ref()
+                        .map(|line| render_with_carets(self, line, &self.position, self.span))
+                }
             }
 
             impl Display for $err_ident {
@@ -58,7 +91,7 @@ macro_rules! define_errors {
                     // } else {
                     //     "".to_string()
                     // };
-                    write!(f, "{}: {}{}", stringify!{$err_desc}, self.msg, self.position)
+                    write!(f, "{}: {}{}", stringify!{$err_desc}, self.msg, self.position.display_one_based())
                 }
             }
 
@@ -90,6 +123,8 @@ macro_rules! define_errors {
                         msg: value.msg,
                         kind: TCalcErrorKind::$err_ident,
                         position: value.position,
+                        span: value.span,
+                        source_context: value.source_context,
                     }
                 }
             }
@@ -109,22 +144,80 @@ pub struct TCalcError {
     msg: String,
     kind: TCalcErrorKind,
     position: InputPosition,
+    span: usize,
+    source_context: Option<String>,
 }
 
 impl TCalcError {
     pub fn with_position(self, position: InputPosition) -> Self {
         Self { position, ..self }
     }
+
+    pub fn with_span(self, span: usize) -> Self {
+        Self { span, ..self }
+    }
+
+    pub fn with_source_context<S: AsRef<str>>(self, line: S) -> Self {
+        // `line` is the text of the line the error occurred on, not the whole input
+        Self {
+            source_context: Some(line.as_ref().to_string()),
+            ..self
+        }
+    }
+
+    pub fn source_context(&self) -> Option<&str> {
+        self.source_context.as_deref()
+    }
+
+    pub fn render<S: AsRef<str>>(&self, source: S) -> String {
+        let line = source
+            .as_ref()
+            .lines()
+            .nth(self.position.line)
+            .unwrap_or_default();
+        render_with_carets(self, line, &self.position, self.span)
+    }
+
+    pub fn render_context(&self) -> Option<String> {
+        // Like `render`, but using the line attached with `with_source_context`
+        self.source_context
+            .as_ref()
+            .map(|line| render_with_carets(self, line, &self.position, self.span))
+    }
+}
+
+fn render_with_carets(
+    error: &dyn Display,
+    line: &str,
+    position: &InputPosition,
+    span: usize,
+) -> String {
+    // The offending `line` with carets under the `span` characters at `position`
+    format!(
+        "{}\n{}\n{}{}",
+        error,
+        line,
+        " ".repeat(position.chr),
+        "^".repeat(span.max(1))
+    )
 }
 
 impl Display for TCalcError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}: {} at {}", self.kind, self.msg, self.position)
+        write!(
+            f,
+            "{}: {} at {}",
+            self.kind,
+            self.msg,
+            self.position.display_one_based()
+        )
     }
 }
 
 impl Error for TCalcError {}
 
+// Lines and characters are counted from 0 internally, but from 1 whenever a position is
+// shown to the user (see `display_one_based`)
 #[derive(Debug, Clone)]
 pub struct InputPosition {
     pub file: String,
@@ -141,6 +234,10 @@ impl InputPosition {
         }
     }
 
+    pub fn display_one_based(&self) -> String {
+        format!("{}:{}:{}", self.file, self.line + 1, self.chr + 1)
+    }
+
     pub fn is_default(self) -> bool {
         self.file == "unknown".to_string() && self.line == 0 && self.chr == 0
     }
@@ -1,18 +1,51 @@
+use std::collections::HashMap;
+
 use crate::core::ast::{Ast, AstNode};
 use crate::core::bitseqs::Bitseq;
-use crate::core::decimals::AngleUnit;
-use crate::core::environment::Environment;
-use crate::core::errors::{SyntaxError, TCalcError};
+use crate::core::decimals::{self, StepBudget};
+use crate::core::environment::{Environment, SharedEnvironment};
+use crate::core::errors::{InputPosition, InvalidOperationError, SyntaxError, TCalcError};
+use crate::core::operators::{CustomOperator, OperatorRegistry};
+use crate::core::patterns;
+use crate::core::suggestions;
 use crate::core::tokens::TokenType;
-use crate::core::values::Value;
+use crate::core::values::{Value, ValueType};
+use crate::core::warnings::Warning;
 use crate::unwrap_or_propagate;
 
 pub struct Evaluator {
     pub environment: Environment,
+    pub custom_operators: OperatorRegistry,
+    pub memoize: bool,
+    pub parallel: bool,
+    pub step_budget: Option<usize>,
+    // Each result is stored with the warnings its evaluation gave
+    memo: HashMap<String, (Value, Vec<Warning>)>,
+    shared_variables: Option<SharedEnvironment>,
+    // The copy of `environment.variables` that parallel workers read, made once per `evaluate`
+    // call and dropped whenever a variable is assigned
+    worker_variables: Option<SharedEnvironment>,
+    warnings: Vec<Warning>,
 }
 
 impl Evaluator {
+    const MEMO_LIMIT: usize = 10_000;
+
     pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_shared_environment(shared: SharedEnvironment) -> Self {
+        // Variables are looked up in and assigned to `shared` instead of `environment.variables`;
+        // the evaluator itself is not `Send`, so each thread builds its own around a clone
+        Self {
+            shared_variables: Some(shared),
+            ..Self::default()
+        }
+    }
+
+    pub fn new_with_debug_vars() -> Self {
+        // Seeds `foo` and `D`, which the sample inputs in main.rs refer to
         let mut n = Self::default();
         n.environment
             .variables
@@ -21,7 +54,125 @@ impl Evaluator {
         n
     }
 
+    pub fn register_operator(&mut self, operator: CustomOperator) {
+        self.custom_operators.register(operator);
+    }
+
+    pub fn clear_memo(&mut self) {
+        self.memo.clear();
+    }
+
+    pub fn take_warnings(&mut self) -> Vec<Warning> {
+        std::mem::take(&mut self.warnings)
+    }
+
+    fn _warn_if_non_terminating(
+        &mut self,
+        operator: &str,
+        left: &Value,
+        right: &Value,
+        position: &InputPosition,
+    ) {
+        let base = self.environment.settings.outbase;
+        if operator == "/" && base != 10 && !left.quotient_terminates_in_base(right, base) {
+            self.warnings.push(Warning::newp(
+                format!("The result does not terminate in base {base} and cannot be shown exactly"),
+                position.clone(),
+            ));
+        }
+    }
+
+    fn _warn_if_width_dropped(
+        &mut self,
+        left: &Value,
+        right: &Value,
+        result: &Value,
+        position: &InputPosition,
+    ) {
+        // Arithmetic on a Bitseq yields an Integer or Decimal, so its leading zeros are lost
+        let is_bitseq = |value: &Value| value.value_type() == ValueType::Bitseq;
+        if (is_bitseq(left) || is_bitseq(right)) && !is_bitseq(result) {
+            self.warnings.push(Warning::newp(
+                format!(
+                    "The result of type {} does not keep the width of its Bitseq operand",
+                    result.value_type()
+                ),
+                position.clone(),
+            ));
+        }
+    }
+
+    fn _canonical_form(&self, node: &AstNode) -> Option<String> {
+        // Prefix notation of the subtree, or None if it is not pure (assignments and
+        // custom operators or functions, which may have side effects)
+        let content = node.token.content_to_string();
+        match node.token.type_ {
+            TokenType::Bitseq | TokenType::Decimal | TokenType::Integer => Some(content),
+            // Shared variables may be reassigned by another thread without clearing the memo
+            TokenType::VariableIdentifier if self.shared_variables.is_some() => None,
+            TokenType::VariableIdentifier => Some(format!("${}", content.to_lowercase())),
+            TokenType::Expression => self._canonical_form(node.subtree.first()?),
+            _ => {
+                // `mem` recalls a different result after every statement
+                if patterns::ASSIGNMENT_OPERATORS.contains(&content.as_str())
+                    || content == "mem"
+                    || self.custom_operators.contains(&content)
+                    || self.environment.functions.contains(&content)
+                {
+                    return None;
+                }
+                let mut form = format!("({}", content);
+                for child in node.subtree.iter() {
+                    form.push(' ');
+                    form.push_str(&self._canonical_form(child)?);
+                }
+                form.push(')');
+                Some(form)
+            }
+        }
+    }
+
+    fn _memo_key(&self, node: &AstNode) -> Option<String> {
+        // Trigonometric results depend on the angle unit, so it is part of every key
+        if !self.memoize || node.token.type_.is_terminal() {
+            return None;
+        }
+        let form = self._canonical_form(node)?;
+        Some(format!("{:?}:{}", self.environment.angle_unit, form))
+    }
+
     pub fn evaluate_node(&mut self, node: &mut AstNode) -> Result<(), TCalcError> {
+        let memo_key = self._memo_key(node);
+        if let Some(key) = &memo_key
+            && let Some((value, warnings)) = self.memo.get(key)
+        {
+            // The same form may be written differently, so its warnings point at the node
+            let position = &node.token.position;
+            self.warnings.extend(
+                warnings
+                    .iter()
+                    .map(|warning| Warning::newp(&warning.msg, position.clone())),
+            );
+            node.value = Some(value.clone());
+            return Ok(());
+        }
+        let first_warning = self.warnings.len();
+        self._evaluate_node_uncached(node)?;
+        if let Some(key) = memo_key
+            && let Some(value) = &node.value
+        {
+            // Starting over once full keeps the memo bounded without tracking which entry
+            // was used least recently
+            if self.memo.len() >= Self::MEMO_LIMIT {
+                self.memo.clear();
+            }
+            let warnings = self.warnings[first_warning..].to_vec();
+            self.memo.insert(key, (value.clone(), warnings));
+        }
+        Ok(())
+    }
+
+    fn _evaluate_node_uncached(&mut self, node: &mut AstNode) -> Result<(), TCalcError> {
         if node.value.is_some() {
             return Ok(()); // No need to evaluate nodes that have already been valued
             // This should not normally happen anyways, so maybe add some reporting?
@@ -40,7 +191,28 @@ impl Evaluator {
             }
             return Ok(());
         }
-        if node.has_children() {
+        if node.token.type_ == TokenType::BinaryOperator
+            && patterns::ASSIGNMENT_OPERATORS.contains(&node.token.content_to_string().as_str())
+        {
+            // The assignee must not be evaluated, so assignments are handled up front
+            return self._evaluate_assignment(node);
+        }
+        if node.token.type_ == TokenType::UnaryFunctionIdentifier
+            && patterns::AGGREGATE_FUNCTIONS.contains(&node.token.content_to_string().as_str())
+        {
+            // The operand may be a list, which has no value of its own
+            return self._evaluate_aggregate_call(node);
+        }
+        if node.token.type_ == TokenType::List {
+            return Err(InvalidOperationError::newp(
+                "A list can only be passed to an aggregate function such as sum or mean",
+                node.token.position.clone(),
+            )
+            .into());
+        }
+        if self._can_evaluate_children_in_parallel(node) {
+            self._evaluate_children_in_parallel(node)?;
+        } else if node.has_children() {
             for child in node.subtree.iter_mut() {
                 self.evaluate_node(child)?;
             }
@@ -51,6 +223,16 @@ impl Evaluator {
         if !node.has_children() {
             panic!("Attempting to evaluate child-less non-terminal AstNode");
         }
+        if node.token.type_.is_expression() {
+            if node.subtree.len() != 1 {
+                panic!(
+                    "Attempting to evaluate expression that has {} children (expected 1)",
+                    node.subtree.len()
+                )
+            }
+            node.value = node.subtree[0].value.clone();
+            return Ok(());
+        }
         if node.token.type_.is_unary() {
             if node.subtree.len() != 1 {
                 panic!(
@@ -94,10 +276,85 @@ impl Evaluator {
         Ok(())
     }
 
+    fn _is_pure(&self, node: &AstNode) -> bool {
+        // Assignments and `mem` touch the session state, and custom operators and functions
+        // may have side effects (and are not `Sync` either)
+        let content = node.token.content_to_string();
+        match node.token.type_ {
+            TokenType::Bitseq
+            | TokenType::Decimal
+            | TokenType::Integer
+            | TokenType::VariableIdentifier => true,
+            _ => {
+                !patterns::ASSIGNMENT_OPERATORS.contains(&content.as_str())
+                    && content != "mem"
+                    && !self.custom_operators.contains(&content)
+                    && !self.environment.functions.contains(&content)
+                    && node.subtree.iter().all(|child| self._is_pure(child))
+            }
+        }
+    }
+
+    fn _can_evaluate_children_in_parallel(&self, node: &AstNode) -> bool {
+        // Terminal children are too cheap to be worth handing to another thread
+        self.parallel
+            && node.subtree.len() == 2
+            && node
+                .subtree
+                .iter()
+                .all(|child| !child.token.type_.is_terminal())
+            && self._is_pure(node)
+    }
+
+    fn _evaluate_children_in_parallel(&mut self, node: &mut AstNode) -> Result<(), TCalcError> {
+        // Each side gets its own evaluator reading the same variables, which is enough as a
+        // pure subtree never refers to the custom operators and functions
+        let shared = match &self.shared_variables {
+            Some(shared) => shared.clone(),
+            None => self
+                .worker_variables
+                .get_or_insert_with(|| SharedEnvironment::new(self.environment.variables.clone()))
+                .clone(),
+        };
+        let angle_unit = self.environment.angle_unit;
+        let settings = self.environment.settings;
+        let budget = decimals::current_step_budget();
+        let worker = || {
+            let mut evaluator = Self::with_shared_environment(shared.clone());
+            evaluator.environment.angle_unit = angle_unit;
+            evaluator.environment.settings = settings;
+            evaluator.parallel = true;
+            evaluator
+        };
+        let evaluate = |node: &mut AstNode| {
+            let mut evaluator = worker();
+            let previous_budget = decimals::replace_step_budget(budget.clone());
+            let result = evaluator.evaluate_node(node);
+            decimals::replace_step_budget(previous_budget);
+            (result, evaluator.warnings)
+        };
+        let (left, right) = node.subtree.split_at_mut(1);
+        let ((left, left_warnings), (right, right_warnings)) =
+            rayon::join(|| evaluate(&mut left[0]), || evaluate(&mut right[0]));
+        self.warnings.extend(left_warnings);
+        self.warnings.extend(right_warnings);
+        left?;
+        right?;
+        Ok(())
+    }
+
     pub fn evaluate(&mut self, ast: &mut Ast) -> Result<(), TCalcError> {
-        for node in ast.iter_mut() {
-            self.evaluate_node(node)?;
+        // All statements of the tree draw on the same budget of series iterations, folding
+        // included, which leaves an operation unfolded once the budget runs out
+        let budget = self.step_budget.map(StepBudget::new);
+        let previous_budget = decimals::replace_step_budget(budget);
+        if self._can_simplify() {
+            ast.simplify();
         }
+        let result = ast.iter_mut().try_for_each(|node| self.evaluate_node(node));
+        decimals::replace_step_budget(previous_budget);
+        self.worker_variables = None;
+        result?;
         // - Resolve subexpressions to values (if any)
         // - Resolve numerals to values
         // if let Err(e) = self._evaluate_numerals(ast) {
@@ -114,6 +371,27 @@ impl Evaluator {
         Ok(())
     }
 
+    fn _can_simplify(&self) -> bool {
+        // Folding reads literals in base 10 and applies the builtin operators, and it does not
+        // warn about quotients that do not terminate in `\outbase`
+        let settings = &self.environment.settings;
+        settings.inbase == 10
+            && settings.outbase == 10
+            && !patterns::UNARY_OPERATORS
+                .iter()
+                .chain(patterns::BINARY_OPERATORS)
+                .any(|symbol| self.custom_operators.contains(symbol))
+    }
+
+    pub fn evaluate_value(&mut self, mut ast: Ast) -> Result<Value, TCalcError> {
+        // With several top-level statements, the value of the last one is the result
+        self.evaluate(&mut ast)?;
+        match ast.last().and_then(|node| node.value.clone()) {
+            Some(value) => Ok(value),
+            None => Err(SyntaxError::new("Expression did not evaluate to a value").into()),
+        }
+    }
+
     fn _evaluate_numeral(&mut self, node: &mut AstNode) -> Result<(), SyntaxError> {
         // if !node.token.type_.is_numeral() {
         //     panic!(
@@ -121,12 +399,15 @@ impl Evaluator {
         //         node.token.type_, node.token.position
         //     );
         // }
-        match Value::from_str(&node.token.content_to_string()) {
+        let inbase = self.environment.settings.inbase;
+        match Value::from_str_in_base(&node.token.content_to_string(), inbase) {
             Ok(v) => {
                 node.value = Some(v);
                 Ok(())
             }
-            Err(e) => Err(e.with_position(node.token.position.clone())),
+            Err(e) => Err(e
+                .with_position(node.token.position.clone())
+                .with_span(node.token.content.len())),
         }
     }
 
@@ -138,18 +419,61 @@ impl Evaluator {
         //     )
         // }
         let identifier = node.token.content_to_string();
-        match self.environment.variables.get(&identifier) {
-            Some(value) => node.value = Some(value.clone()),
+        if identifier.starts_with(patterns::IDENTIFIER_PREFIX_CHAR) {
+            // Settings are read from the environment rather than stored as variables
+            match self.environment.settings.get(&identifier) {
+                Some(value) => node.value = Some(value),
+                None => {
+                    return Err(SyntaxError::newp(
+                        format!("The setting \"{identifier}\" is undefined"),
+                        node.token.position.clone(),
+                    )
+                    .with_span(node.token.content.len()));
+                }
+            }
+            return Ok(());
+        }
+        match self._get_variable(&identifier) {
+            Some(value) => node.value = Some(value),
             None => {
+                let hint = match suggestions::closest_builtin_function(&identifier) {
+                    Some(name) => format!("; did you mean '{name}'?"),
+                    None => String::new(),
+                };
                 return Err(SyntaxError::newp(
-                    format!("The variable \"{identifier}\" is undefined"),
+                    format!("The variable \"{identifier}\" is undefined{hint}"),
                     node.token.position.clone(),
-                ));
+                )
+                .with_span(node.token.content.len()));
             }
         }
         Ok(())
     }
 
+    fn _get_variable(&self, identifier: &str) -> Option<Value> {
+        match &self.shared_variables {
+            Some(shared) => shared.get(identifier),
+            None => self.environment.variables.get(identifier).cloned(),
+        }
+    }
+
+    fn _is_protected_variable(&self, identifier: &str) -> bool {
+        match &self.shared_variables {
+            Some(shared) => shared.read().is_protected(identifier),
+            None => self.environment.variables.is_protected(identifier),
+        }
+    }
+
+    fn _set_variable(&mut self, identifier: &str, value: Value) -> bool {
+        match &self.shared_variables {
+            Some(shared) => shared.set(identifier, value),
+            None => {
+                self.worker_variables = None;
+                self.environment.variables.set(identifier, value)
+            }
+        }
+    }
+
     fn _evaluate_unary_operator(&mut self, node: &mut AstNode) -> Result<(), TCalcError> {
         // pub const UNARY_OPERATORS: &[&str] = &["+", "-", "!", "¬", "~"];
         let operand = node.subtree[0].value.as_ref().unwrap();
@@ -175,15 +499,74 @@ impl Evaluator {
     fn _evaluate_unary_function_call(&mut self, node: &mut AstNode) -> Result<(), TCalcError> {
         // pub const BUILTIN_UNARY_FUNCTIONS: &[&str] = &[
         //     "abs", "not", "sin", "cos", "tan", "cot", "sec", "csc", "exp", "ln", "lg", "log", "sqrt",
-        //     "cbrt", "mem",
+        //     "cbrt", "mem", "signed", "popcount", "clz", "ctz", "parity", "log2", "log10", "asin", "acos",
+        //     "atan", "asinh", "acosh", "atanh",
         // ];
         let operand = node.subtree[0].value.as_ref().unwrap();
         let func_identifier = node.token.content_to_string();
+        if let Some(function) = self.environment.functions.get(&func_identifier) {
+            node.value = Some(function.call(std::slice::from_ref(operand))?);
+            return Ok(());
+        }
         println!("Evaluating unary function {func_identifier}( {operand} )");
+        let unit = self.environment.angle_unit;
         let result = match func_identifier.as_str() {
             "abs" => operand.abs(),
+            "frac" => operand.frac(),
+            "recip" => operand.recip()?,
             "not" => operand.logical_neg(),
-            "sin" => operand.sin(AngleUnit::Degrees).unwrap(),
+            "sin" => operand.sin(unit)?,
+            "cos" => operand.cos(unit)?,
+            "tan" => operand.tan(unit)?,
+            "cot" => operand.cot(unit)?,
+            "sec" => operand.sec(unit)?,
+            "csc" => operand.csc(unit)?,
+            "asin" => operand.asin(unit)?,
+            "acos" => operand.acos(unit)?,
+            "atan" => operand.atan(unit)?,
+            "asinh" => operand.asinh()?,
+            "acosh" => operand.acosh()?,
+            "atanh" => operand.atanh()?,
+            "deg" => operand.deg()?,
+            "rad" => operand.rad()?,
+            "exp" => operand.exp()?,
+            "ln" => operand.ln()?,
+            "log" => operand.log()?,
+            "log2" => operand.log2()?,
+            "log10" => operand.log10()?,
+            "sqrt" => operand.sqrt()?,
+            "signed" => operand.signed()?,
+            "popcount" => operand.popcount()?,
+            "clz" => operand.clz()?,
+            "ctz" => operand.ctz()?,
+            "parity" => operand.parity()?,
+            "bits" => operand.bits()?,
+            "mem" => self.environment.recall_result(operand)?,
+            "isprime" => operand.isprime()?,
+            "factors" => return Err(Self::_text_function_error(&func_identifier)),
+            "int" => operand.cast(ValueType::Integer)?,
+            "dec" => operand.cast(ValueType::Decimal)?,
+            // A hex literal keeps its written width, so that bseq(0x0F) has eight bits
+            "bseq" => match Self::_literal_numeral(&node.subtree[0])
+                .and_then(|numeral| Value::bitseq_from_str(&numeral).ok())
+            {
+                Some(bitseq) => bitseq,
+                None => operand.cast(ValueType::Bitseq)?,
+            },
+            "frombase" => {
+                // Only reached without a digit string, as frombase(n, "digits") is read as a
+                // literal by the parser
+                return Err(SyntaxError::new(
+                    "frombase takes a base and a quoted digit string, as in frombase(36, \"Z\")",
+                )
+                .into());
+            }
+            "tobase" => {
+                return Err(SyntaxError::new(
+                    "tobase takes a base and a value, as in tobase(36, 35)",
+                )
+                .into());
+            }
             _ => {
                 return Err(SyntaxError::new(format!(
                     "The function \"{func_identifier}\" is undefined"
@@ -195,17 +578,214 @@ impl Evaluator {
         Ok(())
     }
 
-    fn _evaluate_binary_operator(&mut self, node: &mut AstNode) -> Result<(), SyntaxError> {
+    fn _evaluate_aggregate_call(&mut self, node: &mut AstNode) -> Result<(), TCalcError> {
+        // sum, mean, stdev, min and max over the elements of a list literal, or over a single
+        // value taken as a list of one
+        let func_identifier = node.token.content_to_string();
+        let elements: &mut [AstNode] = match Self::_list_operand(&mut node.subtree[0]) {
+            Some(list) => &mut list.subtree,
+            None => &mut node.subtree[..1],
+        };
+        for element in elements.iter_mut() {
+            self.evaluate_node(element)?;
+        }
+        let values: Vec<Value> = match elements.iter().map(|e| e.value.clone()).collect() {
+            Some(values) => values,
+            None => return Err(SyntaxError::new("Expression did not evaluate to a value").into()),
+        };
+        let result = match func_identifier.as_str() {
+            "sum" => Value::sum(&values),
+            "mean" => Value::mean(&values),
+            "stdev" => Value::stdev(&values),
+            "min" => Ok(Value::minimum(&values)),
+            "max" => Ok(Value::maximum(&values)),
+            _ => {
+                return Err(SyntaxError::newp(
+                    format!("The function \"{func_identifier}\" is undefined"),
+                    node.token.position.clone(),
+                )
+                .into());
+            }
+        };
+        node.value = Some(unwrap_or_propagate!(
+            result,
+            position: node.token.position.clone()
+        ));
+        Ok(())
+    }
+
+    fn _list_operand(node: &mut AstNode) -> Option<&mut AstNode> {
+        // The list literal the operand consists of, looking through parentheses
+        match node.token.type_ {
+            TokenType::Expression if node.subtree.len() == 1 => {
+                Self::_list_operand(&mut node.subtree[0])
+            }
+            TokenType::List => Some(node),
+            _ => None,
+        }
+    }
+
+    fn _text_function_error(func_identifier: &str) -> TCalcError {
+        // The session renders calls to text functions when they are a whole statement, so
+        // they only reach the evaluator as part of a larger expression
+        InvalidOperationError::new(format!(
+            "{func_identifier} gives text rather than a number, so it can only be the outermost call of a statement"
+        ))
+        .into()
+    }
+
+    fn _literal_numeral(node: &AstNode) -> Option<String> {
+        // The numeral the node was written as, looking through parentheses
+        match node.token.type_ {
+            TokenType::Expression if node.subtree.len() == 1 => {
+                Self::_literal_numeral(&node.subtree[0])
+            }
+            type_ if type_.is_numeral() => Some(node.token.content_to_string()),
+            _ => None,
+        }
+    }
+
+    fn _evaluate_binary_operator(&mut self, node: &mut AstNode) -> Result<(), TCalcError> {
+        let left = node.subtree[0].value.as_ref().unwrap();
+        let right = node.subtree[1].value.as_ref().unwrap();
+        let operator = node.token.content_to_string();
+        let result = self._apply_binary_operator(&operator, left, right)?;
+        let (left, right) = (left.clone(), right.clone());
+        self._warn_if_non_terminating(&operator, &left, &right, &node.token.position);
+        self._warn_if_width_dropped(&left, &right, &result, &node.token.position);
+        node.value = Some(result);
+        Ok(())
+    }
+
+    fn _apply_binary_operator(
+        &self,
+        operator: &str,
+        left: &Value,
+        right: &Value,
+    ) -> Result<Value, TCalcError> {
         // pub const BINARY_OPERATORS: &[&str] = &[
-        //     "^", "*", "/", "%", "+", "-", "<=>", "<=", ">=", ":=", "<<<", ">>>", "<<", ">>", "<", ">",
-        //     "!=", "==", "&&", "||", "??", "!?", "&", "|", "^|",
+        //     "^", "*", "/", "//", "%", "+", "-", "<=>", "<=", ">=", ":=", "<<<", ">>>", "<<", ">>", "<", ">",
+        //     "!=", "==", "&&", "||", "??", "!?", "&", "|", "^|", "+=", "-=", "*=", "/=",
         // ];
-        todo!()
+        if let Some(custom_operator) = self.custom_operators.get(operator) {
+            return custom_operator.call(left, right);
+        }
+        let result = match operator {
+            "^" => left.pow(right)?,
+            "+" => left.add(right)?,
+            "-" => left.sub(right)?,
+            "*" => left.mul(right)?,
+            "/" => left.div(right)?,
+            "//" => left.floor_div(right)?,
+            "%" => left.rem(right)?,
+            "<<" => left.shl(right)?,
+            ">>" => left.shr(right)?,
+            _ => {
+                return Err(
+                    SyntaxError::new(format!("The operator \"{operator}\" is undefined")).into(),
+                );
+            }
+        };
+        Ok(result)
+    }
+
+    fn _evaluate_assignment(&mut self, node: &mut AstNode) -> Result<(), TCalcError> {
+        // M := N stores N under the identifier M and evaluates to N,
+        // M += N (and -=, *=, /=) is shorthand for M := M + N
+        if node.subtree.len() != 2 {
+            panic!(
+                "Attempting to evaluate assignment that has {} children (expected 2)",
+                node.subtree.len()
+            )
+        }
+        if !node.subtree[0].token.type_.is_variable_identifier() {
+            return Err(SyntaxError::newp(
+                "The left-hand side of an assignment must be a variable",
+                node.subtree[0].token.position.clone(),
+            )
+            .into());
+        }
+        let identifier = node.subtree[0].token.content_to_string();
+        if self._is_protected_variable(&identifier) {
+            return Err(InvalidOperationError::newp(
+                format!(
+                    "\"{identifier}\" is a protected built-in constant and cannot be reassigned"
+                ),
+                node.subtree[0].token.position.clone(),
+            )
+            .with_span(node.subtree[0].token.content.len())
+            .into());
+        }
+        self.evaluate_node(&mut node.subtree[1])?;
+        let operator = node.token.content_to_string();
+        let right = node.subtree[1].value.clone().unwrap();
+        let value = if operator == ":=" {
+            right
+        } else {
+            self._evaluate_variable(&mut node.subtree[0])?;
+            let current = node.subtree[0].value.as_ref().unwrap();
+            let arithmetic_operator = operator.trim_end_matches('=');
+            let result = unwrap_or_propagate!(
+                self._apply_binary_operator(arithmetic_operator, current, &right),
+                position: node.token.position.clone()
+            );
+            let current = current.clone();
+            self._warn_if_non_terminating(
+                arithmetic_operator,
+                &current,
+                &right,
+                &node.token.position,
+            );
+            self._warn_if_width_dropped(&current, &right, &result, &node.token.position);
+            result
+        };
+        if identifier.starts_with(patterns::IDENTIFIER_PREFIX_CHAR) {
+            unwrap_or_propagate!(
+                self.environment.settings.set(&identifier, &value),
+                position: node.subtree[0].token.position.clone()
+            );
+        } else if !self._set_variable(&identifier, value.clone()) {
+            return Err(InvalidOperationError::newp(
+                format!("The variable \"{identifier}\" is readonly"),
+                node.token.position.clone(),
+            )
+            .into());
+        }
+        // Memoized results may depend on the variable's previous value
+        self.memo.clear();
+        if node.subtree[0].value.is_none() {
+            node.subtree[0].value = Some(value.clone());
+        }
+        node.value = Some(value);
+        Ok(())
     }
 
-    fn _evaluate_binary_function_call(&mut self, node: &mut AstNode) -> Result<(), SyntaxError> {
-        // M rt N, M logb N, M choose N
-        todo!()
+    fn _evaluate_binary_function_call(&mut self, node: &mut AstNode) -> Result<(), TCalcError> {
+        // M rt N, M logb N, M choose N, M cat N, M bit N, M beta N, M hypot N
+        let left = node.subtree[0].value.as_ref().unwrap();
+        let right = node.subtree[1].value.as_ref().unwrap();
+        let func_identifier = node.token.content_to_string();
+        if let Some(function) = self.environment.functions.get(&func_identifier) {
+            node.value = Some(function.call(&[left.clone(), right.clone()])?);
+            return Ok(());
+        }
+        let result = match func_identifier.as_str() {
+            "rt" => left.root(right)?,
+            "logb" => left.logb(right)?,
+            "cat" => left.concat(right)?,
+            "bit" => left.bit(right)?,
+            "beta" => left.beta(right)?,
+            "hypot" => left.hypot(right)?,
+            "tobase" => return Err(Self::_text_function_error(&func_identifier)),
+            _ => {
+                return Err(SyntaxError::new(format!(
+                    "The function \"{func_identifier}\" is undefined"
+                ))
+                .into());
+            }
+        };
+        node.value = Some(result);
+        Ok(())
     }
 
     fn _evaluate_variables(&mut self, ast: &mut Ast) -> Result<(), SyntaxError> {
@@ -243,6 +823,43 @@ impl Default for Evaluator {
     fn default() -> Self {
         Self {
             environment: Environment::default(),
+            custom_operators: OperatorRegistry::default(),
+            memoize: false,
+            parallel: false,
+            step_budget: None,
+            memo: HashMap::new(),
+            shared_variables: None,
+            worker_variables: None,
+            warnings: Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::functions::FunctionRegistry;
+    use crate::core::integers::Integer;
+    use crate::core::parser::Parser;
+
+    #[test]
+    fn the_memo_stays_within_its_limit() {
+        let mut parser = Parser::new();
+        let mut evaluator = Evaluator::new();
+        evaluator.memoize = true;
+        evaluator
+            .environment
+            .variables
+            .set("x", Value::from(Integer::from(2u32)));
+        let mut largest = 0;
+        for n in 0..Evaluator::MEMO_LIMIT + 10 {
+            let ast = parser
+                .parse(format!("x * {n}"), 0, 0, &FunctionRegistry::default())
+                .unwrap();
+            evaluator.evaluate_value(ast).unwrap();
+            largest = largest.max(evaluator.memo.len());
         }
+        assert_eq!(largest, Evaluator::MEMO_LIMIT);
+        assert!(evaluator.memo.len() < Evaluator::MEMO_LIMIT);
     }
 }
@@ -0,0 +1,47 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::core::errors::TCalcError;
+use crate::core::values::Value;
+
+pub trait Function {
+    fn name(&self) -> &str;
+    fn arity(&self) -> usize;
+    fn call(&self, args: &[Value]) -> Result<Value, TCalcError>;
+}
+
+#[derive(Clone, Default)]
+pub struct FunctionRegistry {
+    functions: HashMap<String, Rc<dyn Function>>,
+}
+
+impl FunctionRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register(&mut self, function: Rc<dyn Function>) -> bool {
+        // Only unary and binary functions can be expressed in the syntax
+        if function.arity() != 1 && function.arity() != 2 {
+            return false;
+        }
+        self.functions.insert(function.name().to_string(), function);
+        true
+    }
+
+    pub fn unregister<S: AsRef<str>>(&mut self, name: S) -> Option<Rc<dyn Function>> {
+        self.functions.remove(name.as_ref())
+    }
+
+    pub fn get<S: AsRef<str>>(&self, name: S) -> Option<&Rc<dyn Function>> {
+        self.functions.get(name.as_ref())
+    }
+
+    pub fn contains<S: AsRef<str>>(&self, name: S) -> bool {
+        self.functions.contains_key(name.as_ref())
+    }
+
+    pub fn arity_of<S: AsRef<str>>(&self, name: S) -> Option<usize> {
+        self.functions.get(name.as_ref()).map(|f| f.arity())
+    }
+}
@@ -1,11 +1,11 @@
 use std::cmp::{Eq, Ord, Ordering, PartialEq, PartialOrd};
 use std::fmt::Display;
-use std::ops::{Add, Neg};
+use std::ops::{Add, Mul, Neg, Sub};
 
-use fastnum::I512;
+use fastnum::{I512, U512, U1024};
 
 use crate::core::bitseqs::{Bitseq, BitseqT};
-use crate::core::decimals::Decimal;
+use crate::core::decimals::{self, Decimal};
 use crate::core::errors::{ConversionError, InvalidOperationError, SyntaxError};
 
 pub type IntegerT = I512;
@@ -45,9 +45,11 @@ impl Integer {
     pub fn from_str_radix<S: AsRef<str>>(src: S, radix: u32) -> Result<Self, SyntaxError> {
         match IntegerT::from_str_radix(src.as_ref(), radix) {
             Ok(value) => Ok(Self { value }),
-            Err(_) => Err(SyntaxError::new(
+            Err(_) => Err(SyntaxError::new(format!(
                 "Failed to parse string \"{}\" of base {} into Integer",
-            )),
+                src.as_ref(),
+                radix
+            ))),
         }
     }
 
@@ -81,11 +83,227 @@ impl Integer {
             value: self.value.abs(),
         }
     }
+
+    pub fn checked_shl(&self, n: u32) -> Option<Self> {
+        // Shifting left by n is multiplying by 2^n, which may exceed the Integer type
+        let factor = IntegerT::TWO.checked_pow(n)?;
+        Some(Self {
+            value: self.value.checked_mul(factor)?,
+        })
+    }
+
+    pub fn bit_width(&self) -> Result<u32, InvalidOperationError> {
+        // Bits needed to write the value in binary, at least one so that 0 needs a bit as well
+        if self.value < IntegerT::ZERO {
+            return Err(InvalidOperationError::new(format!(
+                "Cannot determine the bit width of the negative value {}",
+                self
+            )));
+        }
+        Ok((IntegerT::BITS - self.value.leading_zeros()).max(1))
+    }
+
+    pub fn is_prime(&self) -> bool {
+        // Miller-Rabin with the primes below 100 as witnesses, which is deterministic below
+        // 3.3 * 10^24 and leaves a chance below 4^-25 of passing a composite above that
+        if self.value < IntegerT::TWO {
+            return false;
+        }
+        let n = widen(self.value.unsigned_abs().digits());
+        let one = U1024::ONE;
+        for p in SMALL_PRIMES {
+            let p = U1024::from_u8(p);
+            if n == p {
+                return true;
+            }
+            if n % p == U1024::ZERO {
+                return false;
+            }
+        }
+        let n_minus_one = n - one;
+        let twos = n_minus_one.trailing_zeros();
+        let odd_part = n_minus_one >> twos;
+        'witnesses: for a in SMALL_PRIMES {
+            let mut x = pow_mod(U1024::from_u8(a), odd_part, n);
+            if x == one || x == n_minus_one {
+                continue;
+            }
+            for _ in 1..twos {
+                // Both factors are below n < 2^511, so the product fits in 1024 bits
+                x = (x * x) % n;
+                if x == n_minus_one {
+                    continue 'witnesses;
+                }
+            }
+            return false;
+        }
+        true
+    }
+
+    pub fn prime_factors(&self) -> Result<Vec<(Self, u32)>, InvalidOperationError> {
+        // Primes dividing the magnitude with their multiplicities, in ascending order. Trial
+        // division tries 2, 3, 5 and then only the numbers coprime to 30, each drawing on the
+        // step budget, and stops at MAX_TRIAL_DIVISOR, past which the rest has to be prime
+        if self.value == IntegerT::ZERO {
+            return Err(InvalidOperationError::new("0 has no prime factorization"));
+        }
+        let mut remaining = self.value.unsigned_abs();
+        let mut factors = Vec::new();
+        let candidates = [2, 3, 5]
+            .into_iter()
+            .chain((0u64..).flat_map(|k| WHEEL_RESIDUES.map(|r| 30 * k + r)));
+        for candidate in candidates {
+            let divisor = U512::from_u64(candidate);
+            if divisor * divisor > remaining {
+                break;
+            }
+            if candidate > MAX_TRIAL_DIVISOR {
+                if !Self::_from_magnitude(remaining).is_prime() {
+                    return Err(InvalidOperationError::new(format!(
+                        "{} has prime factors too large to be found by trial division",
+                        self
+                    )));
+                }
+                break;
+            }
+            decimals::spend_step()?;
+            let mut exponent = 0;
+            while remaining % divisor == U512::ZERO {
+                remaining /= divisor;
+                exponent += 1;
+            }
+            if exponent > 0 {
+                factors.push((Self::_from_magnitude(divisor), exponent));
+            }
+        }
+        if remaining > U512::ONE {
+            factors.push((Self::_from_magnitude(remaining), 1));
+        }
+        Ok(factors)
+    }
+
+    fn _from_magnitude(magnitude: U512) -> Self {
+        // Only for magnitudes that divide the magnitude of an Integer and are below 2^511
+        Self {
+            value: IntegerT::from_bits(magnitude),
+        }
+    }
+
+    pub fn checked_pow(&self, exponent: u32) -> Option<Self> {
+        Some(Self {
+            value: self.value.checked_pow(exponent)?,
+        })
+    }
+
+    pub fn shr(&self, n: u32) -> Self {
+        // Arithmetic shift, so negative values round towards negative infinity
+        let value = match self.value.checked_shr(n) {
+            Some(value) => value,
+            None if self.value < IntegerT::ZERO => -IntegerT::ONE,
+            None => IntegerT::ZERO,
+        };
+        Self { value }
+    }
+
+    pub fn div_floor(&self, divisor: &Self) -> Self {
+        // Rounds towards negative infinity, where dividing the inner values truncates
+        let quotient = self.value / divisor.value;
+        let inexact = self.value % divisor.value != IntegerT::ZERO;
+        let negative = (self.value < IntegerT::ZERO) != (divisor.value < IntegerT::ZERO);
+        Self {
+            value: if inexact && negative {
+                quotient - IntegerT::ONE
+            } else {
+                quotient
+            },
+        }
+    }
+
+    pub fn rem_floor(&self, divisor: &Self) -> Self {
+        // Takes the sign of the divisor, so that a == a.div_floor(b) * b + a.rem_floor(b)
+        Self {
+            value: self.value - self.div_floor(divisor).value * divisor.value,
+        }
+    }
+
+    pub fn quotient_terminates_in_base(&self, divisor: &Self, base: u32) -> bool {
+        // The quotient terminates iff every prime factor of its reduced denominator divides
+        // the base, so strip factors shared with the base until none are left
+        if divisor.value == IntegerT::ZERO {
+            return true;
+        }
+        let mut denominator = (divisor.value / gcd(self.value, divisor.value)).abs();
+        let base = IntegerT::from_u32(base);
+        loop {
+            let common = gcd(denominator, base);
+            if common == IntegerT::ONE {
+                break;
+            }
+            denominator /= common;
+        }
+        denominator == IntegerT::ONE
+    }
+
+    pub fn to_string_base(self, radix: u32) -> String {
+        // Sign and magnitude, e.g. -ff, rather than the two's complement of the inner type
+        let digits = self.value.unsigned_abs().to_str_radix(radix);
+        if self.value < IntegerT::ZERO {
+            format!("-{digits}")
+        } else {
+            digits
+        }
+    }
+
+    pub fn popcount(&self) -> u32 {
+        // Counts the set bits of the magnitude, so the sign does not matter
+        self.value.unsigned_abs().count_ones()
+    }
+}
+
+const SMALL_PRIMES: [u8; 25] = [
+    2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89, 97,
+];
+
+// Residues modulo 30 that are coprime to 30, from 7 so that 1 is left out of the first round
+const WHEEL_RESIDUES: [u64; 8] = [7, 11, 13, 17, 19, 23, 29, 31];
+const MAX_TRIAL_DIVISOR: u64 = 1 << 20;
+
+fn widen(digits: &[u64; 8]) -> U1024 {
+    // Twice the width of IntegerT, so that products of two magnitudes cannot overflow
+    let mut wide = [0u64; 16];
+    wide[..8].copy_from_slice(digits);
+    U1024::from_digits(wide)
+}
+
+fn pow_mod(mut base: U1024, mut exponent: U1024, modulus: U1024) -> U1024 {
+    let mut result = U1024::ONE;
+    base %= modulus;
+    while exponent != U1024::ZERO {
+        if exponent & U1024::ONE == U1024::ONE {
+            result = (result * base) % modulus;
+        }
+        base = (base * base) % modulus;
+        exponent >>= 1;
+    }
+    result
+}
+
+fn gcd(a: IntegerT, b: IntegerT) -> IntegerT {
+    let (mut a, mut b) = (a.abs(), b.abs());
+    while b != IntegerT::ZERO {
+        (a, b) = (b, a % b);
+    }
+    a
 }
 
 impl Display for Integer {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.value)
+        // Width, fill, alignment and the sign flag are honoured through `pad_integral`
+        let digits = self.value.to_string();
+        match digits.strip_prefix('-') {
+            Some(magnitude) => f.pad_integral(false, "", magnitude),
+            None => f.pad_integral(true, "", &digits),
+        }
     }
 }
 
@@ -95,10 +313,11 @@ impl Into<IntegerT> for Integer {
     }
 }
 
+// Every u128 fits in the 512 bits of IntegerT, so the conversions below cannot fail
 impl From<Bitseq> for Integer {
     fn from(value: Bitseq) -> Self {
         Self {
-            value: IntegerT::from_u128(value.into()).unwrap(),
+            value: IntegerT::from_u128(value.integer_part()).unwrap(),
         }
     }
 }
@@ -111,6 +330,22 @@ impl From<BitseqT> for Integer {
     }
 }
 
+impl From<u32> for Integer {
+    fn from(value: u32) -> Self {
+        Self {
+            value: IntegerT::from_u32(value),
+        }
+    }
+}
+
+impl From<i128> for Integer {
+    fn from(value: i128) -> Self {
+        Self {
+            value: IntegerT::from_i128(value).unwrap(),
+        }
+    }
+}
+
 impl From<bool> for Integer {
     fn from(value: bool) -> Self {
         Self {
@@ -124,12 +359,20 @@ impl TryFrom<Decimal> for Integer {
 
     fn try_from(value: Decimal) -> Result<Self, Self::Error> {
         use crate::core::decimals::DecimalT;
-        let raw: DecimalT = value.into();
+        // Trailing zeros after the point, as in 2.0, do not make a fractional part
+        let mut raw: DecimalT = value.into();
+        if raw.fractional_digits_count() > 0 {
+            raw = raw.reduce();
+        }
         if raw.fractional_digits_count() > 0 {
             return Err(ConversionError::new(
                 "Cannot convert Decimal with a fractional part to Integer",
             ));
         }
+        // A negative scale would be written out in exponent notation, e.g. 1e+100
+        if raw.fractional_digits_count() < 0 {
+            raw = raw.rescale(0);
+        }
         match IntegerT::from_str(&raw.to_string()) {
             Ok(value) => Ok(Self { value }),
             Err(_) => Err(ConversionError::new(
@@ -176,3 +419,59 @@ impl Add for Integer {
         }
     }
 }
+
+impl Sub for Integer {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        Self {
+            value: self.value - rhs.value,
+        }
+    }
+}
+
+impl Mul for Integer {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        Self {
+            value: self.value * rhs.value,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn integer(digits: &str) -> Integer {
+        Integer::from_str_radix(digits, 10).unwrap()
+    }
+
+    #[test]
+    fn primes_are_told_from_composites() {
+        for n in [
+            "2",
+            "3",
+            "97",
+            "2305843009213693951",
+            "170141183460469231731687303715884105727",
+        ] {
+            assert!(integer(n).is_prime(), "{n}");
+        }
+        // 561 is a Carmichael number, 3215031751 a strong pseudoprime to the bases 2, 3, 5 and 7,
+        // and the last the product of two Mersenne primes
+        for n in [
+            "0",
+            "1",
+            "4",
+            "91",
+            "561",
+            "3215031751",
+            "1427247692705959880439315947500961989719490561",
+        ] {
+            assert!(!integer(n).is_prime(), "{n}");
+        }
+        assert!(!Integer::from(-97i128).is_prime());
+    }
+}
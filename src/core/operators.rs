@@ -0,0 +1,189 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::rc::Rc;
+
+use crate::core::errors::TCalcError;
+use crate::core::patterns;
+use crate::core::values::Value;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Associativity {
+    Left,
+    Right,
+}
+
+impl Display for Associativity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Left => "Left",
+                Self::Right => "Right",
+            }
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct OperatorTable {
+    groups: Vec<Vec<String>>,
+    associativities: HashMap<String, Associativity>,
+}
+
+impl OperatorTable {
+    pub fn new() -> Self {
+        Self {
+            groups: Vec::new(),
+            associativities: HashMap::new(),
+        }
+    }
+
+    pub fn groups(&self) -> &[Vec<String>] {
+        &self.groups
+    }
+
+    pub fn insert<S: AsRef<str>>(
+        &mut self,
+        symbol: S,
+        precedence: usize,
+        associativity: Associativity,
+    ) {
+        // `precedence` is the index of the group the operator joins, 0 binding tightest
+        let symbol = symbol.as_ref().to_string();
+        self.remove(&symbol);
+        while self.groups.len() <= precedence {
+            self.groups.push(Vec::new());
+        }
+        self.groups[precedence].push(symbol.clone());
+        self.associativities.insert(symbol, associativity);
+    }
+
+    pub fn remove<S: AsRef<str>>(&mut self, symbol: S) {
+        let symbol = symbol.as_ref();
+        for group in self.groups.iter_mut() {
+            group.retain(|op| op != symbol);
+        }
+        self.associativities.remove(symbol);
+    }
+
+    pub fn contains<S: AsRef<str>>(&self, symbol: S) -> bool {
+        self.associativities.contains_key(symbol.as_ref())
+    }
+
+    pub fn precedence_of<S: AsRef<str>>(&self, symbol: S) -> Option<usize> {
+        let symbol = symbol.as_ref();
+        self.groups
+            .iter()
+            .position(|group| group.iter().any(|op| op == symbol))
+    }
+
+    pub fn associativity_of<S: AsRef<str>>(&self, symbol: S) -> Option<Associativity> {
+        self.associativities.get(symbol.as_ref()).copied()
+    }
+
+    pub fn set_associativity<S: AsRef<str>>(&mut self, symbol: S, associativity: Associativity) {
+        if let Some(assoc) = self.associativities.get_mut(symbol.as_ref()) {
+            *assoc = associativity;
+        }
+    }
+
+    pub fn swap_groups(&mut self, a: usize, b: usize) {
+        self.groups.swap(a, b);
+    }
+}
+
+impl Default for OperatorTable {
+    fn default() -> Self {
+        let mut table = Self::new();
+        for (precedence, group) in patterns::BINARY_OPERATOR_PRECEDENCE.iter().enumerate() {
+            for symbol in group {
+                let associativity =
+                    if patterns::RIGHT_ASSOCIATIVE_OPERATORS.contains(&symbol.as_str()) {
+                        Associativity::Right
+                    } else {
+                        Associativity::Left
+                    };
+                table.insert(symbol, precedence, associativity);
+            }
+        }
+        table
+    }
+}
+
+pub type BinaryOperatorFn = dyn Fn(&Value, &Value) -> Result<Value, TCalcError>;
+
+#[derive(Clone)]
+pub struct CustomOperator {
+    pub symbol: String,
+    pub precedence: usize,
+    pub associativity: Associativity,
+    pub function: Rc<BinaryOperatorFn>,
+}
+
+impl CustomOperator {
+    // `precedence` is the index of the precedence group the operator joins, 0 binding tightest
+    pub fn new<S, F>(
+        symbol: S,
+        precedence: usize,
+        associativity: Associativity,
+        function: F,
+    ) -> Self
+    where
+        S: AsRef<str>,
+        F: Fn(&Value, &Value) -> Result<Value, TCalcError> + 'static,
+    {
+        Self {
+            symbol: symbol.as_ref().to_string(),
+            precedence,
+            associativity,
+            function: Rc::new(function),
+        }
+    }
+
+    pub fn call(&self, left: &Value, right: &Value) -> Result<Value, TCalcError> {
+        (self.function)(left, right)
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct OperatorRegistry {
+    operators: HashMap<String, CustomOperator>,
+}
+
+impl OperatorRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register(&mut self, operator: CustomOperator) {
+        self.operators.insert(operator.symbol.clone(), operator);
+    }
+
+    pub fn unregister<S: AsRef<str>>(&mut self, symbol: S) -> Option<CustomOperator> {
+        self.operators.remove(symbol.as_ref())
+    }
+
+    pub fn get<S: AsRef<str>>(&self, symbol: S) -> Option<&CustomOperator> {
+        self.operators.get(symbol.as_ref())
+    }
+
+    pub fn contains<S: AsRef<str>>(&self, symbol: S) -> bool {
+        self.operators.contains_key(symbol.as_ref())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &CustomOperator> {
+        self.operators.values()
+    }
+
+    pub fn match_at(&self, input: &[char], start: usize) -> Option<&CustomOperator> {
+        // Longest registered symbol that the input continues with at `start`
+        self.operators
+            .values()
+            .filter(|op| {
+                let symbol: Vec<char> = op.symbol.chars().collect();
+                !symbol.is_empty() && input[start..].starts_with(&symbol)
+            })
+            .max_by_key(|op| op.symbol.chars().count())
+    }
+}
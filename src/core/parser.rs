@@ -1,17 +1,38 @@
 use crate::core::ast::{Ast, AstNode};
 use crate::core::errors::{InputPosition, SyntaxError};
+use crate::core::functions::FunctionRegistry;
+use crate::core::integers::Integer;
+use crate::core::operators::{Associativity, CustomOperator, OperatorRegistry, OperatorTable};
 use crate::core::patterns;
 use crate::core::tokens::{Token, TokenType};
 
 pub struct Parser {
     pub ast: Ast,
+    pub custom_operators: OperatorRegistry,
+    pub operator_table: OperatorTable,
+    pub casefold: bool,
+    pub max_depth: usize,
+    pub max_tree_depth: usize,
 }
 
 impl Parser {
+    // Each level of parentheses is parsed recursively, so bound it well below stack exhaustion
+    const DEFAULT_MAX_DEPTH: usize = 256;
+    // Evaluation recurses once per node on the way down, and an operator chain such as
+    // `1+1+…+1` nests as deeply as it is long, so the whole tree is bounded as well
+    const DEFAULT_MAX_TREE_DEPTH: usize = 512;
+
     pub fn new() -> Self {
         Self::default()
     }
 
+    pub fn with_operator_table(operator_table: OperatorTable) -> Self {
+        Self {
+            operator_table,
+            ..Default::default()
+        }
+    }
+
     pub fn reset(&mut self) {
         self.ast = Ast::new();
     }
@@ -20,9 +41,23 @@ impl Parser {
         std::mem::take(&mut self.ast)
     }
 
-    fn _copy_while(input: &Vec<char>, charset: &str, start: usize, buf: &mut Vec<char>) {
+    pub fn register_operator(&mut self, operator: CustomOperator) {
+        self.operator_table.insert(
+            &operator.symbol,
+            operator.precedence,
+            operator.associativity,
+        );
+        self.custom_operators.register(operator);
+    }
+
+    fn _copy_while(
+        input: &[char],
+        predicate: impl Fn(char) -> bool,
+        start: usize,
+        buf: &mut Vec<char>,
+    ) {
         for character in &input[start..] {
-            if charset.contains(*character) {
+            if predicate(*character) {
                 buf.push(*character);
             } else {
                 break;
@@ -30,6 +65,292 @@ impl Parser {
         }
     }
 
+    fn _unprefixed_numeral_len(input: &[char], start: usize) -> usize {
+        // Digits and separators, followed by an exponent only if its marker (and optional
+        // sign) is directly followed by a digit, so `2e3` is a numeral but `2e` is not
+        let is_mantissa_char = |c: char| {
+            c.is_ascii_digit() || c == '_' || patterns::FRACTIONAL_SEPARATOR_CHARS.contains(c)
+        };
+        let mut j = start;
+        while j < input.len() && is_mantissa_char(input[j]) {
+            j += 1;
+        }
+        if j < input.len() && matches!(input[j], 'e' | 'E') {
+            let mut k = j + 1;
+            if k < input.len() && patterns::EXPONENT_SIGN_CHARS.contains(input[k]) {
+                k += 1;
+            }
+            if k < input.len() && input[k].is_ascii_digit() {
+                while k < input.len() && (input[k].is_ascii_digit() || input[k] == '_') {
+                    k += 1;
+                }
+                j = k;
+            }
+        }
+        j - start
+    }
+
+    fn _second_fractional_separator(buf: &[char]) -> Option<usize> {
+        // Index of a fractional separator that is preceded by another one in the numeral
+        buf.iter()
+            .enumerate()
+            .filter(|(_, c)| patterns::FRACTIONAL_SEPARATOR_CHARS.contains(**c))
+            .nth(1)
+            .map(|(j, _)| j)
+    }
+
+    fn _split_arguments(content: &[char], numeral_commas: bool) -> Vec<(usize, String)> {
+        // Top-level arguments with the offset they start at. Inside the parentheses of a
+        // function call and the brackets of a list a comma always separates, so `log(2,8)` is
+        // log base 2 of 8 and fractions there are written with '.'. Elsewhere `numeral_commas`
+        // keeps a comma between two digits as a fractional separator, as in `(3,5)`
+        let mut arguments = Vec::new();
+        let mut depth: usize = 0;
+        let mut start: usize = 0;
+        for (j, c) in content.iter().enumerate() {
+            match *c {
+                '(' | patterns::LIST_OPENING_CHAR => depth += 1,
+                ')' | patterns::LIST_CLOSING_CHAR => depth = depth.saturating_sub(1),
+                patterns::ARGUMENT_SEPARATOR_CHAR if depth == 0 => {
+                    let in_numeral = numeral_commas
+                        && j > 0
+                        && content[j - 1].is_ascii_digit()
+                        && content.get(j + 1).is_some_and(|c| c.is_ascii_digit());
+                    if !in_numeral {
+                        arguments.push((start, content[start..j].iter().collect()));
+                        start = j + 1;
+                    }
+                }
+                _ => {}
+            }
+        }
+        arguments.push((start, content[start..].iter().collect()));
+        arguments
+    }
+
+    fn _parse_argument_list(
+        &self,
+        tree: &mut Ast,
+        i: usize,
+        arguments: Vec<(usize, String)>,
+        line: usize,
+        functions: &FunctionRegistry,
+    ) -> Result<(), SyntaxError> {
+        // `log(b, x)` becomes `(b logb x)` and `tobase(n, x)` a binary call as well, while
+        // `frombase(n, "digits")` becomes the Integer literal those digits stand for in base n
+        // and `max(a, b, c)` the aggregate of the list `[a, b, c]`
+        let position = tree[i].token.position.clone();
+        let function = match i.checked_sub(1).map(|j| &tree[j].token) {
+            Some(token) if token.type_ == TokenType::UnaryFunctionIdentifier => {
+                token.content_to_string()
+            }
+            _ => {
+                return Err(SyntaxError::newp(
+                    "Arguments can only be separated in a function call",
+                    position,
+                ));
+            }
+        };
+        if function == "frombase" && arguments.len() == 2 {
+            let literal =
+                self._parse_frombase_literal(tree, &arguments, line, &position, functions)?;
+            let mut call = Ast::new();
+            call.push_token(literal);
+            tree[i].set_subtree(call);
+            tree.remove(i - 1);
+            return Ok(());
+        }
+        if patterns::AGGREGATE_FUNCTIONS.contains(&function.as_str()) {
+            // The parentheses are read as brackets, so the node now at `i` is parsed as a list
+            tree[i].token.type_ = TokenType::List;
+            return Ok(());
+        }
+        let binary_function = match (function.as_str(), arguments.len()) {
+            ("log", 2) => "logb",
+            ("tobase", 2) => "tobase",
+            _ => {
+                return Err(SyntaxError::newp(
+                    format!(
+                        "The function \"{function}\" does not take {} arguments",
+                        arguments.len()
+                    ),
+                    position,
+                ));
+            }
+        };
+        let operands = self._parse_elements(
+            arguments,
+            tree.level() + 1,
+            line,
+            &position,
+            "argument",
+            functions,
+        )?;
+        let mut call = Ast::new();
+        call.push_subtree(
+            Token::new_implicit(
+                TokenType::BinaryFunctionIdentifier,
+                binary_function.chars().collect(),
+                tree[i - 1].token.position.clone(),
+            ),
+            operands,
+        );
+        tree[i].set_subtree(call);
+        tree.remove(i - 1);
+        Ok(())
+    }
+
+    fn _parse_list(
+        &self,
+        node: &mut AstNode,
+        level: usize,
+        line: usize,
+        functions: &FunctionRegistry,
+    ) -> Result<(), SyntaxError> {
+        // Each element of `[a, b, c]` becomes a child of the List node
+        let position = node.token.position.clone();
+        let elements = Self::_split_arguments(&node.token.content, false);
+        if elements.len() == 1 && elements[0].1.trim().is_empty() {
+            return Err(SyntaxError::newp("Empty list", position));
+        }
+        let items =
+            self._parse_elements(elements, level, line, &position, "list element", functions)?;
+        node.set_subtree(items);
+        Ok(())
+    }
+
+    fn _parse_elements(
+        &self,
+        elements: Vec<(usize, String)>,
+        level: usize,
+        line: usize,
+        position: &InputPosition,
+        description: &str,
+        functions: &FunctionRegistry,
+    ) -> Result<Ast, SyntaxError> {
+        // Each element is parsed at the level it ends up at, so that nesting is bounded and
+        // a leading sign is not taken to continue the previous result
+        let mut items = Ast::new();
+        for (start, element) in elements {
+            let chr = position.chr + 1 + start;
+            let mut subtree = Ast::new();
+            subtree.relevel_from(level);
+            self._parse_recursively(element, line, chr, &mut subtree, functions)?;
+            if subtree.len() != 1 {
+                return Err(SyntaxError::newp(
+                    format!("Expected a single expression as {description}"),
+                    InputPosition::new("unknown", line, chr),
+                ));
+            }
+            items.push(subtree.remove(0));
+        }
+        Ok(items)
+    }
+
+    fn _parse_frombase_literal(
+        &self,
+        tree: &Ast,
+        arguments: &[(usize, String)],
+        line: usize,
+        position: &InputPosition,
+        functions: &FunctionRegistry,
+    ) -> Result<Token, SyntaxError> {
+        // The base has to be a whole number literal and the digits a quoted string, as the
+        // digits of bases above 16 could not be told apart from identifiers otherwise
+        let (base_start, base_argument) = &arguments[0];
+        let base_position = InputPosition::new("unknown", line, position.chr + 1 + base_start);
+        let mut subtree = Ast::new();
+        subtree.relevel_from(tree.level() + 1);
+        self._parse_recursively(
+            base_argument.clone(),
+            line,
+            base_position.chr,
+            &mut subtree,
+            functions,
+        )?;
+        let base_number: Option<Integer> = match subtree.first().map(|node| node.literal_value()) {
+            Some(Some(value)) if subtree.len() == 1 => value.try_into().ok(),
+            _ => None,
+        };
+        let base = base_number
+            .and_then(|number| (2..=36).find(|n| Integer::from(*n) == number))
+            .ok_or_else(|| {
+                SyntaxError::newp(
+                    "The base of frombase must be a whole number from 2 to 36",
+                    base_position.clone(),
+                )
+            })?;
+
+        let (digits_start, digits_argument) = &arguments[1];
+        let offset = digits_argument.len() - digits_argument.trim_start().len();
+        let digits_position =
+            InputPosition::new("unknown", line, position.chr + 1 + digits_start + offset);
+        let quoted = digits_argument.trim();
+        let digits = quoted
+            .strip_prefix(patterns::DIGIT_STRING_QUOTE_CHAR)
+            .and_then(|rest| rest.strip_suffix(patterns::DIGIT_STRING_QUOTE_CHAR))
+            .ok_or_else(|| {
+                SyntaxError::newp(
+                    "The digits of frombase must be given as a quoted string, as in frombase(36, \"Z\")",
+                    digits_position.clone(),
+                )
+                .with_span(quoted.chars().count())
+            })?
+            .replace('_', "");
+        let value = if !digits.is_empty() && digits.chars().all(|c| c.is_ascii_alphanumeric()) {
+            Integer::from_str_radix(&digits, base).ok()
+        } else {
+            None
+        };
+        match value {
+            Some(value) => Ok(Token::new_implicit(
+                TokenType::Integer,
+                value.to_string().chars().collect(),
+                position.clone(),
+            )),
+            None => Err(SyntaxError::newp(
+                format!("The string {quoted} is not a valid numeral in base {base}"),
+                digits_position,
+            )
+            .with_span(quoted.chars().count())),
+        }
+    }
+
+    fn _check_nesting_depth(
+        &self,
+        depth: usize,
+        position: &InputPosition,
+    ) -> Result<(), SyntaxError> {
+        if depth > self.max_depth {
+            return Err(SyntaxError::newp(
+                format!(
+                    "Expression nesting is too deep (at most {} levels are supported)",
+                    self.max_depth
+                ),
+                position.clone(),
+            ));
+        }
+        Ok(())
+    }
+
+    fn _check_tree_depth(&self, tree: &Ast) -> Result<(), SyntaxError> {
+        // Measured without recursing, as the tree may be too deep for that
+        let mut pending: Vec<(&AstNode, usize)> = tree.iter().map(|node| (node, 1)).collect();
+        while let Some((node, depth)) = pending.pop() {
+            if depth > self.max_tree_depth {
+                return Err(SyntaxError::newp(
+                    format!(
+                        "Expression is too deeply nested (at most {} levels of operations are supported)",
+                        self.max_tree_depth
+                    ),
+                    node.token.position.clone(),
+                ));
+            }
+            pending.extend(node.subtree.iter().map(|child| (child, depth + 1)));
+        }
+        Ok(())
+    }
+
     fn _copy_matchedspan(
         input: &Vec<char>,
         opening_char: char,
@@ -65,38 +386,109 @@ impl Parser {
         input: S,
         line: usize,
         chr: usize,
+        functions: &FunctionRegistry,
     ) -> Result<Ast, SyntaxError> {
+        // Identifiers registered in `functions`, normally those of the evaluator's
+        // environment, are read as calls to those functions
         let input = input.as_ref().to_string();
-        if let Err(e) = Self::_parse_recursively(input, line, chr, &mut self.ast) {
-            return Err(e);
+        // `input` starts at column `chr`, so indent it by as much to keep the carets aligned
+        let source_context = format!("{}{}", " ".repeat(chr), input);
+        let mut tree = self.take_ast();
+        if let Err(e) = self
+            ._parse_recursively(input, line, chr, &mut tree, functions)
+            .and_then(|_| self._check_tree_depth(&tree))
+        {
+            return Err(e.with_source_context(source_context));
+        }
+        // Subtrees are assembled without their levels, so set them in a single pass here
+        let level = tree.level();
+        tree.relevel_from(level);
+        if tree.is_empty() {
+            return Err(SyntaxError::newp(
+                "Empty expression",
+                InputPosition::new("unknown", line, chr),
+            )
+            .with_source_context(source_context));
         }
-        Ok(self.take_ast())
+        Ok(tree)
+    }
+
+    pub fn lex(input: &str) -> Result<Vec<Token>, SyntaxError> {
+        // Flat token stream in source order, without resolving operators or building subtrees.
+        // Parenthesised expressions yield their Expression token followed by their inner tokens.
+        let mut tokens: Vec<Token> = Vec::new();
+        Self::default()._lex_recursively(input.to_string(), 0, 0, 0, &mut tokens)?;
+        Ok(tokens)
+    }
+
+    fn _lex_recursively(
+        &self,
+        input: String,
+        line: usize,
+        chr: usize,
+        depth: usize,
+        tokens: &mut Vec<Token>,
+    ) -> Result<(), SyntaxError> {
+        let mut tree = Ast::new();
+        self.tokenize(input, line, chr, &mut tree, &FunctionRegistry::default())?;
+        for node in tree {
+            if node.token.type_ == TokenType::Expression {
+                self._check_nesting_depth(depth + 1, &node.token.position)?;
+                let content = node.token.content_to_string();
+                let chr = node.token.position.chr + 1;
+                tokens.push(node.token);
+                self._lex_recursively(content, line, chr, depth + 1, tokens)?;
+            } else {
+                tokens.push(node.token);
+            }
+        }
+        Ok(())
     }
 
     fn _parse_recursively(
+        &self,
         input: String,
         line: usize,
         chr: usize,
         tree: &mut Ast,
+        functions: &FunctionRegistry,
     ) -> Result<(), SyntaxError> {
-        if let Err(e) = Self::tokenize(input, line, chr, tree) {
-            return Err(e);
-        }
+        self.tokenize(input, line, chr, tree, functions)?;
         let mut i: usize = 0;
         while i < tree.len() {
-            if tree[i].token.type_ == TokenType::Expression {
+            if tree[i].token.type_ == TokenType::List {
+                let level = tree.level() + 1;
+                self._check_nesting_depth(level, &tree[i].token.position)?;
+                self._parse_list(&mut tree[i], level, line, functions)?;
+            } else if tree[i].token.type_ == TokenType::Expression {
+                self._check_nesting_depth(tree.level() + 1, &tree[i].token.position)?;
+                let is_call = i > 0 && tree[i - 1].token.type_.is_function_identifier();
+                let arguments = Self::_split_arguments(&tree[i].token.content, !is_call);
+                if arguments.len() > 1 {
+                    // The function identifier is folded into the Expression, or the Expression
+                    // is turned into a List, so the node now at `i` has not been looked at yet
+                    self._parse_argument_list(tree, i, arguments, line, functions)?;
+                    continue;
+                }
                 let mut subtree = Ast::new();
                 subtree.relevel_from(tree.level() + 1);
-                match Self::_parse_recursively(
+                match self._parse_recursively(
                     tree[i].token.content_to_string(),
                     line,
                     tree[i].token.position.chr + 1,
                     &mut subtree,
+                    functions,
                 ) {
                     Err(e) => {
                         return Err(e);
                     }
                     Ok(_) => {
+                        if subtree.is_empty() {
+                            return Err(SyntaxError::newp(
+                                "Empty parentheses",
+                                tree[i].token.position.clone(),
+                            ));
+                        }
                         tree[i].set_subtree(subtree);
                     }
                 }
@@ -104,30 +496,24 @@ impl Parser {
             i += 1;
         }
 
-        if let Err(e) = Self::disambiguate_operators(tree) {
-            return Err(e);
-        }
+        Self::disambiguate_operators(tree)?;
 
-        if let Err(e) = Self::expose_implicit_multiplications(tree) {
-            return Err(e);
-        }
+        Self::expose_implicit_multiplications(tree)?;
 
-        if let Err(e) = Self::expose_implicit_mem0_call(tree) {
-            return Err(e);
-        }
+        Self::expose_implicit_mem0_call(tree)?;
 
-        if let Err(e) = Self::incorporate_operands(tree) {
-            return Err(e);
-        }
+        Self::incorporate_operands(tree, &self.operator_table)?;
 
         Ok(())
     }
 
     pub fn tokenize(
+        &self,
         input: String,
         line: usize,
         chr: usize,
         tree: &mut Ast,
+        functions: &FunctionRegistry,
     ) -> Result<(), SyntaxError> {
         let input: Vec<char> = input.chars().collect();
         let mut buf: Vec<char> = Vec::new();
@@ -135,6 +521,11 @@ impl Parser {
         while i < input.len() {
             if patterns::IGNORABLE_WHITESPACE_CHARS.contains(input[i]) {
                 // do naught
+            } else if input[i] == patterns::COMMENT_INITIAL_CHAR {
+                // Skip the comment up to the end of the line
+                while i + 1 < input.len() && input[i + 1] != '\n' {
+                    i += 1;
+                }
             } else if input[i] == '(' {
                 // Match TokenType.Expression
                 // Find matching closing parenthesis and consume input along the way
@@ -149,12 +540,77 @@ impl Parser {
                 tree.push_token(token);
                 i += buf.len() + 1; // Skip the closing paren
                 buf.clear();
+            } else if input[i] == patterns::LIST_OPENING_CHAR {
+                // Match TokenType.List, whose elements are parsed like parenthesised expressions
+                if let Err(e) = Self::_copy_matchedspan(
+                    &input,
+                    patterns::LIST_OPENING_CHAR,
+                    patterns::LIST_CLOSING_CHAR,
+                    i + 1,
+                    &mut buf,
+                ) {
+                    return Err(SyntaxError::newp(
+                        e.msg.replace("parenthesis", "bracket"),
+                        InputPosition::new("unknown", line, chr + i),
+                    ));
+                }
+                tree.push_token(Token::new(
+                    TokenType::List,
+                    buf.clone(),
+                    InputPosition::new("unknown", line, chr + i),
+                ));
+                i += buf.len() + 1; // Skip the closing bracket
+                buf.clear();
+            } else if let Some(operator) = self.custom_operators.match_at(&input, i) {
+                // Match custom TokenType.BinaryOperator, which takes priority over builtin tokens
+                let symbol: Vec<char> = operator.symbol.chars().collect();
+                let symbol_len = symbol.len();
+                tree.push_token(Token::new(
+                    TokenType::BinaryOperator,
+                    symbol,
+                    InputPosition::new("unknown", line, chr + i),
+                ));
+                i += symbol_len - 1;
             } else if patterns::NUMERAL_INITIAL_CHARS.contains(input[i]) {
                 // Match TokenType.Numeral
                 buf.push(input[i]);
-                Self::_copy_while(&input, patterns::NUMERAL_INTERNAL_CHARS, i + 1, &mut buf);
+                Self::_copy_while(
+                    &input,
+                    |c| patterns::NUMERAL_INTERNAL_CHARS.contains(c),
+                    i + 1,
+                    &mut buf,
+                );
+                if patterns::BASE_PREFIX.is_match(&buf.iter().collect::<String>()) {
+                    // A base prefix claims all adjacent digits, so that invalid ones are
+                    // reported against the numeral rather than read as an identifier
+                    Self::_copy_while(
+                        &input,
+                        patterns::is_prefixed_numeral_internal_char,
+                        i + buf.len(),
+                        &mut buf,
+                    );
+                } else {
+                    // Letters other than an exponent marker start an identifier, so that `2x`
+                    // and `3e` are read as implicit multiplications
+                    let len = Self::_unprefixed_numeral_len(&input, i);
+                    buf = input[i..i + len].to_vec();
+                }
+                if let Some(j) = Self::_second_fractional_separator(&buf) {
+                    return Err(SyntaxError::newp(
+                        format!(
+                            "A numeral can have only one fractional separator, found another '{}' in \"{}\"",
+                            buf[j],
+                            buf.iter().collect::<String>()
+                        ),
+                        InputPosition::new("unknown", line, chr + i + j),
+                    ));
+                }
+                let buf_string = buf.iter().collect::<String>();
                 let token_type: TokenType;
-                if buf.contains(&'.') || buf.contains(&',') {
+                if buf.contains(&'.')
+                    || buf.contains(&',')
+                    || patterns::DECIMAL_SCIENTIFIC.is_match(&buf_string)
+                {
                     token_type = TokenType::Decimal;
                 } else if buf.starts_with(&['0', 'b']) {
                     token_type = TokenType::Bitseq;
@@ -168,22 +624,40 @@ impl Parser {
                 ));
                 i += buf.len() - 1;
                 buf.clear();
-            } else if patterns::IDENTIFIER_INITIAL_CHARS.contains(input[i]) {
+            } else if patterns::is_identifier_initial_char(input[i]) {
                 // Match TokenType.Identifier
                 buf.push(input[i]);
-                Self::_copy_while(&input, patterns::IDENTIFIER_INTERNAL_CHARS, i + 1, &mut buf);
+                Self::_copy_while(
+                    &input,
+                    patterns::is_identifier_internal_char,
+                    i + 1,
+                    &mut buf,
+                );
                 let token_type: TokenType;
                 let buf_string = buf.iter().collect::<String>();
-                if patterns::BUILTIN_UNARY_FUNCTIONS.contains(&&buf_string.as_str()) {
+                // Under casefold, function names are matched (and stored) in lowercase
+                let func_string = if self.casefold {
+                    buf_string.to_lowercase()
+                } else {
+                    buf_string.clone()
+                };
+                let mut content = buf.clone();
+                if patterns::BUILTIN_UNARY_FUNCTIONS.contains(&func_string.as_str())
+                    || functions.arity_of(&func_string) == Some(1)
+                {
                     token_type = TokenType::UnaryFunctionIdentifier;
-                } else if patterns::BUILTIN_BINARY_FUNCTIONS.contains(&&buf_string.as_str()) {
+                    content = func_string.chars().collect();
+                } else if patterns::BUILTIN_BINARY_FUNCTIONS.contains(&func_string.as_str())
+                    || functions.arity_of(&func_string) == Some(2)
+                {
                     token_type = TokenType::BinaryFunctionIdentifier;
+                    content = func_string.chars().collect();
                 } else {
                     token_type = TokenType::VariableIdentifier;
                 }
                 tree.push_token(Token::new(
                     token_type,
-                    buf.clone(),
+                    content,
                     InputPosition::new("unknown", line, chr + i),
                 ));
                 i += buf.len() - 1;
@@ -191,7 +665,12 @@ impl Parser {
             } else if patterns::OPERATOR_INITIAL_CHARS.contains(input[i]) {
                 // Match TokenType.Operator
                 buf.push(input[i]);
-                Self::_copy_while(&input, patterns::OPERATOR_INTERNAL_CHARS, i + 1, &mut buf);
+                Self::_copy_while(
+                    &input,
+                    |c| patterns::OPERATOR_INTERNAL_CHARS.contains(c),
+                    i + 1,
+                    &mut buf,
+                );
                 let token_type: TokenType;
                 let buf_string = buf.iter().collect::<String>();
                 if patterns::AMBIGUOUS_OPERATORS.contains(&buf_string.as_str()) {
@@ -204,7 +683,8 @@ impl Parser {
                     return Err(SyntaxError::newp(
                         format!("Unknown operator '{}'", buf_string),
                         InputPosition::new("unknown", line, chr + i),
-                    ));
+                    )
+                    .with_span(buf.len()));
                 }
                 tree.push_token(Token::new(
                     token_type,
@@ -218,6 +698,11 @@ impl Parser {
                     "Unexpected closing parenthesis",
                     InputPosition::new("unknown", line, chr + i),
                 ));
+            } else if input[i] == patterns::LIST_CLOSING_CHAR {
+                return Err(SyntaxError::newp(
+                    "Unexpected closing bracket",
+                    InputPosition::new("unknown", line, chr + i),
+                ));
             } else {
                 return Err(SyntaxError::newp(
                     format!("Unknown character '{}'", input[i]),
@@ -247,12 +732,15 @@ impl Parser {
     }
 
     fn expose_implicit_multiplications(tree: &mut Ast) -> Result<(), SyntaxError> {
+        // A function identifier is not a value, so `sin 30` is left as an application of
+        // `sin` to `30` rather than read as `sin * 30`
         let mut i: usize = 0;
         while i + 1 < tree.len() {
             let is_value = match tree[i].token.type_ {
                 TokenType::UnaryOperator => tree[i].token.content == vec!['!'],
                 TokenType::Bitseq
                 | TokenType::Expression
+                | TokenType::List
                 | TokenType::Integer
                 | TokenType::Decimal
                 | TokenType::VariableIdentifier => true,
@@ -262,6 +750,7 @@ impl Parser {
                 TokenType::UnaryOperator => tree[i + 1].token.content != vec!['!'],
                 TokenType::Bitseq
                 | TokenType::Expression
+                | TokenType::List
                 | TokenType::Integer
                 | TokenType::Decimal
                 | TokenType::UnaryFunctionIdentifier
@@ -288,12 +777,13 @@ impl Parser {
             if tree[i].token.type_ == TokenType::AmbiguousOperator {
                 let has_left_value: bool;
                 if i < 1 {
-                    has_left_value = tree.level() == 0;
+                    has_left_value = Self::_continues_last_result(tree);
                 } else {
                     has_left_value = match tree[i - 1].token.type_ {
                         TokenType::UnaryOperator => tree[i - 1].token.content == vec!['!'],
                         TokenType::Bitseq
                         | TokenType::Expression
+                        | TokenType::List
                         | TokenType::Integer
                         | TokenType::Decimal
                         | TokenType::VariableIdentifier => true,
@@ -321,6 +811,7 @@ impl Parser {
                         TokenType::AmbiguousOperator // Will necessarily disambiguate to UnaryOp later
                         | TokenType::Bitseq
                         | TokenType::Expression
+                        | TokenType::List
                         | TokenType::Integer
                         | TokenType::Decimal
                         | TokenType::UnaryFunctionIdentifier
@@ -347,8 +838,19 @@ impl Parser {
         Ok(())
     }
 
+    fn _continues_last_result(tree: &Ast) -> bool {
+        // A top-level statement starting with a binary operator or function continues from the
+        // previous result, unless it is an assignment, whose operands are all on the line
+        tree.level() == 0
+            && !tree.iter().any(|node| {
+                node.token.type_.is_operator()
+                    && patterns::ASSIGNMENT_OPERATORS
+                        .contains(&node.token.content_to_string().as_str())
+            })
+    }
+
     fn expose_implicit_mem0_call(tree: &mut Ast) -> Result<(), SyntaxError> {
-        if tree.level() > 0 || tree.len() < 1 {
+        if tree.is_empty() || !Self::_continues_last_result(tree) {
             return Ok(());
         }
         if tree[0].token.type_ == TokenType::BinaryFunctionIdentifier
@@ -382,132 +884,423 @@ impl Parser {
         )
     }
 
-    pub fn incorporate_operands(tree: &mut Ast) -> Result<(), SyntaxError> {
-        if let Err(e) = Self::_incorporate_factorials(tree) {
-            return Err(e);
-        }
-        if let Err(e) = Self::_incorporate_unary_ops_and_funcs(tree) {
-            return Err(e);
-        }
-        if let Err(e) = Self::_incorporate_binary_ops(tree) {
-            return Err(e);
+    fn _missing_operand_error(
+        tree: &Ast,
+        i: usize,
+        description: &str,
+        missing_left: bool,
+    ) -> SyntaxError {
+        // Point at the operator and name the token on the other side, if there is one
+        let (side, neighbour) = if missing_left {
+            ("left-hand", tree.get(i + 1).map(|node| ("before", node)))
+        } else {
+            ("right-hand", i.checked_sub(1).map(|j| ("after", &tree[j])))
+        };
+        let context = match neighbour {
+            Some((preposition, node)) => {
+                let text = if node.token.type_.is_expression() {
+                    format!("({})", node.token.content_to_string())
+                } else {
+                    node.token.content_to_string()
+                };
+                format!(" {} '{}'", preposition, text)
+            }
+            None => String::new(),
+        };
+        SyntaxError::newp(
+            format!(
+                "{} '{}' is missing a {} operand{}",
+                description,
+                tree[i].token.content_to_string(),
+                side,
+                context
+            ),
+            tree[i].token.position.clone(),
+        )
+    }
+
+    pub fn incorporate_operands(
+        tree: &mut Ast,
+        operator_table: &OperatorTable,
+    ) -> Result<(), SyntaxError> {
+        Self::_reject_function_assignments(tree)?;
+        Self::_incorporate_factorials(tree)?;
+        Self::_incorporate_unary_ops_and_funcs(tree)?;
+        Self::_incorporate_binary_funcs(tree)?;
+        Self::_incorporate_binary_ops(tree, operator_table)?;
+        Ok(())
+    }
+
+    fn _reject_function_assignments(tree: &Ast) -> Result<(), SyntaxError> {
+        // A function name would otherwise take the assignment operator as its operand
+        for pair in tree.windows(2) {
+            let (target, operator) = (&pair[0], &pair[1]);
+            if target.token.type_.is_function_identifier()
+                && !target.has_children()
+                && operator.token.type_ == TokenType::BinaryOperator
+                && patterns::ASSIGNMENT_OPERATORS
+                    .contains(&operator.token.content_to_string().as_str())
+            {
+                let name = target.token.content_to_string();
+                let kind = if patterns::BUILTIN_UNARY_FUNCTIONS.contains(&name.as_str())
+                    || patterns::BUILTIN_BINARY_FUNCTIONS.contains(&name.as_str())
+                {
+                    "a built-in function"
+                } else {
+                    "a function"
+                };
+                return Err(SyntaxError::newp(
+                    format!("'{name}' is {kind} and cannot be used as a variable"),
+                    target.token.position.clone(),
+                )
+                .with_span(target.token.content.len()));
+            }
         }
         Ok(())
     }
 
+    fn _restore_and_report(
+        tree: &mut Ast,
+        mut before: Vec<AstNode>,
+        node: AstNode,
+        after: Vec<AstNode>,
+        description: &str,
+        missing_left: bool,
+    ) -> SyntaxError {
+        // Put the partially rebuilt nodes back so the error can name the operator's neighbours
+        let i = before.len();
+        before.push(node);
+        before.extend(after);
+        **tree = before;
+        Self::_missing_operand_error(tree, i, description, missing_left)
+    }
+
     fn _incorporate_factorials(tree: &mut Ast) -> Result<(), SyntaxError> {
         // Go LTR so that "x! !"" -> (((x)!)!)
-        let mut i: usize = 0;
-        while i < tree.len() {
-            if tree[i].token.type_ == TokenType::UnaryOperator && tree[i].token.content == &['!'] {
-                if i < 1 {
-                    return Err(SyntaxError::newp(
-                        "Unary operator '!' is missing a left-hand operand",
-                        tree[i].token.position.clone(),
-                    ));
+        let mut output: Vec<AstNode> = Vec::with_capacity(tree.len());
+        let mut nodes = std::mem::take(&mut **tree).into_iter();
+        while let Some(mut node) = nodes.next() {
+            if node.token.type_ == TokenType::UnaryOperator && node.token.content == ['!'] {
+                match output.pop() {
+                    Some(operand) => {
+                        // The operator node takes the place of its operand
+                        node.set_subtree(Ast::from(operand));
+                    }
+                    None => {
+                        return Err(Self::_restore_and_report(
+                            tree,
+                            output,
+                            node,
+                            nodes.collect(),
+                            "Unary operator",
+                            true,
+                        ));
+                    }
                 }
-                i -= 1;
-                let mut subtree = Ast::new();
-                subtree.push(tree.remove(i));
-                subtree.relevel_from(tree.level() + 1);
-                tree[i].set_subtree(subtree);
             }
-            i += 1;
+            output.push(node);
         }
-
-        return Ok(());
+        **tree = output;
+        Ok(())
     }
 
     fn _incorporate_unary_ops_and_funcs(tree: &mut Ast) -> Result<(), SyntaxError> {
         // Go RTL so that "- +x" -> "(-(+(x)))"
-        let mut i: usize = tree.len();
-        if i < 1 {
-            return Ok(());
-        }
-        loop {
-            i -= 1;
-            if (tree[i].token.type_ == TokenType::UnaryOperator && tree[i].token.content != &['!'])
-                || tree[i].token.type_ == TokenType::UnaryFunctionIdentifier
+        // A function without parentheses applies to the single node after it, and binds tighter
+        // than any binary operator: "sqrt 9 + 1" -> "(sqrt(9)) + 1", "sqrt 2^4" -> "(sqrt(2))^4"
+        // The output is collected back to front and reversed at the end
+        let mut output: Vec<AstNode> = Vec::with_capacity(tree.len());
+        let mut nodes = std::mem::take(&mut **tree).into_iter().rev();
+        while let Some(mut node) = nodes.next() {
+            if (node.token.type_ == TokenType::UnaryOperator && node.token.content != ['!'])
+                || node.token.type_ == TokenType::UnaryFunctionIdentifier
             {
-                let operand_i = i + 1;
-                if operand_i >= tree.len() {
-                    return Err(SyntaxError::newp(
-                        format!(
-                            "Unary operator '{}' is missing a right-hand operand",
-                            tree[i].token.content_to_string()
-                        ),
-                        tree[i].token.position.clone(),
-                    ));
+                match output.pop() {
+                    Some(operand) => {
+                        node.set_subtree(Ast::from(operand));
+                    }
+                    None => {
+                        let description = if node.token.type_.is_function_identifier() {
+                            "Unary function"
+                        } else {
+                            "Unary operator"
+                        };
+                        return Err(Self::_restore_and_report(
+                            tree,
+                            nodes.rev().collect(),
+                            node,
+                            Vec::new(),
+                            description,
+                            false,
+                        ));
+                    }
                 }
-                let mut subtree = Ast::from(tree.remove(operand_i));
-                subtree.relevel_from(tree.level() + 1);
-                tree[i].set_subtree(subtree);
-                // We've pruned the tree behind the direction we're going, so no need to adjust i
-            }
-            if i == 0 {
-                break;
             }
+            output.push(node);
         }
+        output.reverse();
+        **tree = output;
         Ok(())
     }
 
-    fn _incorporate_binary_ops(tree: &mut Ast) -> Result<(), SyntaxError> {
-        for op_set in patterns::BINARY_OPERATOR_PRECEDENCE.iter() {
-            if let Err(e) = Self::_incorporate_binary_op_set(tree, op_set) {
-                return Err(e);
+    fn _incorporate_binary_ltr<F>(
+        tree: &mut Ast,
+        is_operator: F,
+        description: &str,
+    ) -> Result<(), SyntaxError>
+    where
+        F: Fn(&AstNode) -> bool,
+    {
+        let mut output: Vec<AstNode> = Vec::with_capacity(tree.len());
+        let mut nodes = std::mem::take(&mut **tree).into_iter();
+        while let Some(mut node) = nodes.next() {
+            if is_operator(&node) {
+                let right = if output.is_empty() {
+                    None
+                } else {
+                    nodes.next()
+                };
+                match right {
+                    Some(right) => {
+                        let left = output.pop().unwrap();
+                        node.set_subtree(Ast::from(vec![left, right]));
+                    }
+                    None => {
+                        let missing_left = output.is_empty();
+                        return Err(Self::_restore_and_report(
+                            tree,
+                            output,
+                            node,
+                            nodes.collect(),
+                            description,
+                            missing_left,
+                        ));
+                    }
+                }
             }
+            output.push(node);
         }
+        **tree = output;
         Ok(())
     }
 
-    fn _incorporate_binary_op_set(tree: &mut Ast, binops: &Vec<String>) -> Result<(), SyntaxError> {
-        // Go RTL so that "a * b / c" -> "((a) * ((b) / (c)))"
-        let mut i: usize = tree.len();
-        if i < 1 {
-            return Ok(());
-        }
-        loop {
-            i -= 1;
-            if tree[i].token.type_ == TokenType::BinaryOperator
-                && binops.contains(&tree[i].token.content_to_string())
-            {
-                if i == 0 {
-                    return Err(SyntaxError::newp(
-                        format!(
-                            "Binary operator '{}' is missing a left-hand operand",
-                            tree[i].token.content_to_string()
-                        ),
-                        tree[i].token.position.clone(),
-                    ));
-                }
-                let left_operand_i: usize = i - 1;
-                let right_operand_i: usize = i + 1;
-                if right_operand_i >= tree.len() {
-                    return Err(SyntaxError::newp(
-                        format!(
-                            "Binary operator '{}' is missing a right-hand operand",
-                            tree[i].token.content_to_string()
-                        ),
-                        tree[i].token.position.clone(),
-                    ));
+    fn _incorporate_binary_rtl<F>(
+        tree: &mut Ast,
+        is_operator: F,
+        description: &str,
+    ) -> Result<(), SyntaxError>
+    where
+        F: Fn(&AstNode) -> bool,
+    {
+        // The output is collected back to front and reversed at the end
+        let mut output: Vec<AstNode> = Vec::with_capacity(tree.len());
+        let mut nodes = std::mem::take(&mut **tree).into_iter().rev();
+        while let Some(mut node) = nodes.next() {
+            if is_operator(&node) {
+                let left = if output.is_empty() {
+                    None
+                } else {
+                    nodes.next()
+                };
+                match left {
+                    Some(left) => {
+                        let right = output.pop().unwrap();
+                        node.set_subtree(Ast::from(vec![left, right]));
+                    }
+                    None => {
+                        let before: Vec<AstNode> = nodes.rev().collect();
+                        let missing_left = before.is_empty();
+                        output.reverse();
+                        return Err(Self::_restore_and_report(
+                            tree,
+                            before,
+                            node,
+                            output,
+                            description,
+                            missing_left,
+                        ));
+                    }
                 }
-                let mut operands = vec![tree.remove(right_operand_i), tree.remove(left_operand_i)];
-                operands.reverse();
-                let mut subtree = Ast::from(operands);
-                subtree.relevel_from(tree.level() + 1);
-                i -= 1; // Only -1 because we only adjust for the left_operand we removed
-                tree[i].set_subtree(subtree);
-            }
-            if i == 0 {
-                break;
             }
+            output.push(node);
         }
+        output.reverse();
+        **tree = output;
         Ok(())
     }
+
+    fn _incorporate_binary_funcs(tree: &mut Ast) -> Result<(), SyntaxError> {
+        // Binary functions bind tighter than any binary operator, so "a + b cat c" -> "a + (b cat c)"
+        // Go RTL so that "a cat b cat c" -> "((a) cat ((b) cat (c)))"
+        Self::_incorporate_binary_rtl(
+            tree,
+            |node| node.token.type_ == TokenType::BinaryFunctionIdentifier,
+            "Binary function",
+        )
+    }
+
+    fn _incorporate_binary_ops(
+        tree: &mut Ast,
+        operator_table: &OperatorTable,
+    ) -> Result<(), SyntaxError> {
+        for op_set in operator_table.groups() {
+            // Left-associative operators bind tighter than right-associative ones in the same set
+            let (left_binops, right_binops): (Vec<String>, Vec<String>) = op_set
+                .iter()
+                .cloned()
+                .partition(|op| operator_table.associativity_of(op) == Some(Associativity::Left));
+            Self::_incorporate_left_assoc_binary_op_set(tree, &left_binops)?;
+            Self::_incorporate_binary_op_set(tree, &right_binops)?;
+        }
+        Ok(())
+    }
+
+    fn _is_unincorporated_binop(node: &AstNode, binops: &[String]) -> bool {
+        node.token.type_ == TokenType::BinaryOperator
+            && !node.has_children()
+            && binops.contains(&node.token.content_to_string())
+    }
+
+    fn _incorporate_left_assoc_binary_op_set(
+        tree: &mut Ast,
+        binops: &[String],
+    ) -> Result<(), SyntaxError> {
+        // Go LTR so that "a - b - c" -> "(((a) - (b)) - (c))"
+        Self::_incorporate_binary_ltr(
+            tree,
+            |node| Self::_is_unincorporated_binop(node, binops),
+            "Binary operator",
+        )
+    }
+
+    fn _incorporate_binary_op_set(tree: &mut Ast, binops: &[String]) -> Result<(), SyntaxError> {
+        // Go RTL so that "a ^ b ^ c" -> "((a) ^ ((b) ^ (c)))"
+        Self::_incorporate_binary_rtl(
+            tree,
+            |node| Self::_is_unincorporated_binop(node, binops),
+            "Binary operator",
+        )
+    }
 }
 
 impl Default for Parser {
     fn default() -> Self {
-        Self { ast: Ast::new() }
+        Self {
+            ast: Ast::new(),
+            custom_operators: OperatorRegistry::default(),
+            operator_table: OperatorTable::default(),
+            casefold: false,
+            max_depth: Self::DEFAULT_MAX_DEPTH,
+            max_tree_depth: Self::DEFAULT_MAX_TREE_DEPTH,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::*;
+
+    // Operator chains of about 100k characters, as long inputs used to take quadratic time
+    fn parse_long_inputs(report: fn(usize, Duration)) {
+        // Their trees are as deep as they are long, so the limits are lifted and the stack
+        // grown to match
+        let parse = move || {
+            for pattern in ["12+3*4-5", "(1+2)*(3-4)+"] {
+                let input = pattern.repeat(100_000 / pattern.len()) + "1";
+                let mut parser = Parser {
+                    max_tree_depth: usize::MAX,
+                    ..Parser::new()
+                };
+                let start = Instant::now();
+                let ast = parser
+                    .parse(&input, 0, 0, &FunctionRegistry::default())
+                    .unwrap();
+                report(input.len(), start.elapsed());
+                assert_eq!(ast.len(), 1);
+            }
+        };
+        std::thread::Builder::new()
+            .stack_size(1 << 30)
+            .spawn(parse)
+            .unwrap()
+            .join()
+            .unwrap();
+    }
+
+    fn sexpr(input: &str) -> String {
+        Parser::new()
+            .parse(input, 0, 0, &FunctionRegistry::default())
+            .unwrap()
+            .to_sexpr()
+    }
+
+    #[test]
+    fn operators_group_by_their_associativity() {
+        assert_eq!(sexpr("8-3-2"), "(- (- 8 3) 2)");
+        assert_eq!(sexpr("16/4/2"), "(/ (/ 16 4) 2)");
+        assert_eq!(sexpr("7%4%2"), "(% (% 7 4) 2)");
+        assert_eq!(sexpr("2^2^3"), "(^ 2 (^ 2 3))");
+    }
+
+    fn parse_error(input: &str) -> SyntaxError {
+        Parser::new()
+            .parse(input, 0, 0, &FunctionRegistry::default())
+            .err()
+            .unwrap()
+    }
+
+    #[test]
+    fn exponents_take_a_sign() {
+        assert_eq!(sexpr("1e+3"), "1e+3");
+        assert_eq!(sexpr("1.6e-19 * 2"), "(* 1.6e-19 2)");
+        assert_eq!(sexpr("6.02E+23"), "6.02E+23");
+        // Only directly after the exponent mark
+        assert_eq!(sexpr("2e3+1"), "(+ 2e3 1)");
+        parse_error("1e+");
+    }
+
+    #[test]
+    fn numerals_take_one_fractional_separator() {
+        for (input, second) in [("1,5.2", '.'), ("1.5,2", ','), ("5.6.", '.')] {
+            let error = parse_error(input);
+            assert_eq!(
+                error.msg,
+                format!(
+                    "A numeral can have only one fractional separator, found another '{second}' in \"{input}\""
+                )
+            );
+            assert_eq!(error.position.chr, 3);
+        }
+    }
+
+    #[test]
+    fn long_operator_chains_parse() {
+        parse_long_inputs(|_, _| {});
+    }
+
+    // A benchmark rather than a test: cargo test --release -- --ignored --nocapture
+    #[test]
+    #[ignore]
+    fn benchmark_parsing_long_inputs() {
+        parse_long_inputs(|length, elapsed| println!("{length} characters: {elapsed:?}"));
+    }
+
+    #[test]
+    fn frombase_reads_its_digit_string_in_the_base() {
+        assert_eq!(sexpr("frombase(36, \"Z\")"), "35");
+        assert_eq!(sexpr("frombase(36, \"zz\")"), "1295");
+        assert_eq!(sexpr("frombase(3, \"120\") + 1"), "(+ 15 1)");
+        assert_eq!(
+            parse_error("frombase(3, \"3\")").msg,
+            "The string \"3\" is not a valid numeral in base 3"
+        );
+        for base in ["1", "37", "2.5"] {
+            assert_eq!(
+                parse_error(&format!("frombase({base}, \"1\")")).msg,
+                "The base of frombase must be a whole number from 2 to 36"
+            );
+        }
     }
 }
@@ -7,23 +7,27 @@ macro_rules! vec_into {
 
 lazy_static! {
     pub static ref BASE_PREFIX: Regex = Regex::new(r"^0[bBdDoOxX]").unwrap();
-    pub static ref BINARY_INTEGER: Regex = Regex::new(r"^0[bB][01_]*[01]$").unwrap();
+    pub static ref BINARY_INTEGER: Regex = Regex::new(r"^0[bB][01](?:[01_]*[01])?$").unwrap();
     pub static ref BINARY_DECIMAL: Regex =
-        Regex::new(r"^0[bB][01_]*[.,](?:[01_]*[01])?$").unwrap();
+        Regex::new(r"^0[bB](?:[01][01_]*)?[.,](?:[01_]*[01])?$").unwrap();
     pub static ref DECIMAL_INTEGER: Regex =
-        Regex::new(r"^(?:0[dD]_?[0-9]|[0-9])(?:[0-9_]*[0-9])?$").unwrap();
+        Regex::new(r"^(?:0[dD])?[0-9](?:[0-9_]*[0-9])?$").unwrap();
     pub static ref DECIMAL_DECIMAL: Regex =
-        Regex::new(r"^(?:0[dD]_?)?(?:[0-9]*|[0-9][0-9_]*)[.,](?:[0-9]*|[0-9_]*[0-9])$").unwrap();
+        Regex::new(r"^(?:0[dD])?(?:[0-9][0-9_]*[.,](?:[0-9]*|[0-9_]*[0-9])|[.,][0-9](?:[0-9_]*[0-9])?)$").unwrap();
+    pub static ref DECIMAL_SCIENTIFIC: Regex = Regex::new(
+        r"^(?:0[dD])?(?:[0-9][0-9_]*(?:[.,][0-9_]*)?|[.,][0-9][0-9_]*)[eE][+-]?[0-9](?:[0-9_]*[0-9])?$"
+    )
+    .unwrap();
     pub static ref HEXADECIMAL_INTEGER: Regex =
-        Regex::new(r"^0[xX][0-9a-fA-F_]*[0-9a-fA-F]$").unwrap();
+        Regex::new(r"^0[xX][0-9a-fA-F](?:[0-9a-fA-F_]*[0-9a-fA-F])?$").unwrap();
     pub static ref HEXADECIMAL_DECIMAL: Regex =
-        Regex::new(r"^0[xX][0-9a-fA-F_]*[.,](?:[0-9a-fA-F_]*[0-9a-fA-F])?$").unwrap();
-    pub static ref OCTAL_INTEGER: Regex = Regex::new(r"^0[oO][0-7_]*[0-7]$").unwrap();
+        Regex::new(r"^0[xX](?:[0-9a-fA-F][0-9a-fA-F_]*)?[.,](?:[0-9a-fA-F_]*[0-9a-fA-F])?$").unwrap();
+    pub static ref OCTAL_INTEGER: Regex = Regex::new(r"^0[oO][0-7](?:[0-7_]*[0-7])?$").unwrap();
     pub static ref OCTAL_DECIMAL: Regex =
-        Regex::new(r"^0[oO][0-7_]*[.,](?:[0-7_]*[0-7])?$").unwrap();
+        Regex::new(r"^0[oO](?:[0-7][0-7_]*)?[.,](?:[0-7_]*[0-7])?$").unwrap();
     pub static ref BINARY_OPERATOR_PRECEDENCE: Vec<Vec<String>> = vec![
         vec_into!["^"],                          // Exponentiation
-        vec_into!["*", "/", "%"],                // Multiplication, Division, Modulo
+        vec_into!["*", "/", "//", "%"],          // Multiplication, Division, Modulo
         vec_into!["+", "-"],                     // Addition, Subtraction
         vec_into!["<<", ">>", "<<<", ">>>"],     // Bit shifts
         vec_into!["&"],                          // Bitwise and
@@ -31,34 +35,74 @@ lazy_static! {
         vec_into!["^|"],                         // Bitwise xor
         vec_into![">", "<", "<=", ">=", "!=", "==", "<=>", "??", "!?"], // Comparisons
         vec_into!["&&", "||"],                   // Logical conjunction/disjunction
-        vec_into![":="],                         // Assignment
+        vec_into![":=", "+=", "-=", "*=", "/="], // Assignment
     ];
 }
 
 pub const NUMERAL_INITIAL_CHARS: &str = "0123456789.,";
 pub const NUMERAL_INTERNAL_CHARS: &str = "0123456789.,abcdefoxABCDEFOX_";
+pub const EXPONENT_SIGN_CHARS: &str = "+-";
+pub const FRACTIONAL_SEPARATOR_CHARS: &str = ".,";
 pub const IGNORABLE_WHITESPACE_CHARS: &str = " \t";
+pub const COMMENT_INITIAL_CHAR: char = '#';
+pub const STATEMENT_SEPARATOR_CHAR: char = ';';
+// Always separates inside the parentheses of a function call and the brackets of a list, so
+// fractions there are written with '.'. Elsewhere ',' is a fractional separator as in "2,5"
+pub const ARGUMENT_SEPARATOR_CHAR: char = ',';
+pub const LIST_OPENING_CHAR: char = '[';
+pub const LIST_CLOSING_CHAR: char = ']';
+// Encloses the digits passed to frombase, as in frombase(36, "Z")
+pub const DIGIT_STRING_QUOTE_CHAR: char = '"';
 pub const OPERATOR_INITIAL_CHARS: &str = "+-!^*/%¬<>=:&|?~";
 pub const OPERATOR_INTERNAL_CHARS: &str = OPERATOR_INITIAL_CHARS;
-pub const IDENTIFIER_INITIAL_CHARS: &str = "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ\\";
-pub const IDENTIFIER_INTERNAL_CHARS: &str = IDENTIFIER_INITIAL_CHARS;
+pub fn is_prefixed_numeral_internal_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "_.,".contains(c)
+}
+
+pub const IDENTIFIER_PREFIX_CHAR: char = '\\';
+
+pub fn is_identifier_initial_char(c: char) -> bool {
+    c.is_alphabetic() || c == IDENTIFIER_PREFIX_CHAR
+}
+
+pub fn is_identifier_internal_char(c: char) -> bool {
+    c.is_alphabetic() || c.is_ascii_digit()
+}
 
 pub const AMBIGUOUS_OPERATORS: &[&str] = &["+", "-"];
+// Assignments group to the right so that `a := b := 3` assigns 3 to both b and a
+pub const RIGHT_ASSOCIATIVE_OPERATORS: &[&str] = &["^", ":=", "+=", "-=", "*=", "/="];
+pub const ASSIGNMENT_OPERATORS: &[&str] = &[":=", "+=", "-=", "*=", "/="];
 pub const UNARY_OPERATORS: &[&str] = &["+", "-", "!", "¬", "~"];
 pub const BINARY_OPERATORS: &[&str] = &[
-    "^", "*", "/", "%", "+", "-", "<=>", "<=", ">=", ":=", "<<<", ">>>", "<<", ">>", "<", ">",
-    "!=", "==", "&&", "||", "??", "!?", "&", "|", "^|",
+    "^", "*", "/", "//", "%", "+", "-", "<=>", "<=", ">=", ":=", "<<<", ">>>", "<<", ">>", "<",
+    ">", "!=", "==", "&&", "||", "??", "!?", "&", "|", "^|", "+=", "-=", "*=", "/=",
 ];
+// Operators whose results are never Bitseqs, as Bitseq operands are taken as Integers
+pub const ARITHMETIC_OPERATORS: &[&str] = &["^", "*", "/", "//", "%", "+", "-"];
 pub const BUILTIN_UNARY_FUNCTIONS: &[&str] = &[
     "abs", "not", "sin", "cos", "tan", "cot", "sec", "csc", "exp", "ln", "lg", "log", "sqrt",
-    "cbrt", "mem",
+    "cbrt", "mem", "signed", "popcount", "clz", "ctz", "parity", "log2", "log10", "asin", "acos",
+    "atan", "asinh", "acosh", "atanh", "frac", "recip", "deg", "rad", "bits", "frombase", "tobase",
+    "int", "dec", "bseq", "isprime", "factors", "sum", "mean", "stdev", "min", "max",
 ];
-pub const BUILTIN_BINARY_FUNCTIONS: &[&str] = &["rt", "logb", "choose"];
+pub const BUILTIN_BINARY_FUNCTIONS: &[&str] =
+    &["rt", "logb", "choose", "cat", "bit", "beta", "hypot"];
+// Functions giving text rather than a number, which the session shows when one of them is
+// called by a whole statement
+pub const TEXT_FUNCTIONS: &[&str] = &["tobase", "factors"];
+// Functions taking a list literal such as [1, 2, 3], or a single value as a list of one
+pub const AGGREGATE_FUNCTIONS: &[&str] = &["sum", "mean", "stdev", "min", "max"];
+// Statements starting with one of these words are handled by the session, not the parser
+pub const SESSION_COMMANDS: &[&str] = &["vars", "del", "reset", "settings", "builtins", "angle"];
 pub const BUILTIN_VARIABLE_IDENTIFIERS: &[&str] = &[
     "\\inbase",
     "\\outbase",
-    "\\showfracs",
+    "\\casefold",
     "\\precision",
     "pi",
+    "π",
+    "tau",
+    "τ",
     "e",
 ];
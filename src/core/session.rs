@@ -0,0 +1,787 @@
+use std::collections::VecDeque;
+use std::fmt::Display;
+use std::io::{BufRead, Write};
+use std::path::Path;
+
+use crate::core::ast::AstNode;
+use crate::core::decimals::AngleUnit;
+use crate::core::environment::{Environment, EnvironmentSnapshot};
+use crate::core::errors::{InputPosition, InvalidOperationError, SyntaxError, TCalcError};
+use crate::core::evaluator::Evaluator;
+use crate::core::parser::Parser;
+use crate::core::patterns;
+use crate::core::tokens::TokenType;
+use crate::core::values::Value;
+use crate::unwrap_or_propagate;
+
+// What a top-level statement did, so that callers can present each kind differently
+#[derive(Clone)]
+pub enum Outcome {
+    Value(Value),
+    Assignment { name: String, value: Value },
+    Command(Command),
+    // Text rather than a number, from tobase(n, x) or factors(x), or a result in `\outbase`
+    Text(String),
+}
+
+#[derive(Clone)]
+pub enum Command {
+    // User variables and their values, sorted by name
+    Vars(Vec<(String, Value)>),
+    Del(String),
+    Reset,
+    Settings(Vec<(String, String)>),
+    // Names of the built-in functions, operators and variables, by category
+    Builtins(Vec<(String, Vec<String>)>),
+    // What a single built-in name is, once for each category it belongs to
+    Builtin {
+        name: String,
+        descriptions: Vec<String>,
+    },
+    Angle(AngleUnit),
+}
+
+// Category, number of operands, and members, in the order they are listed by `builtins`
+const BUILTIN_CATEGORIES: &[(&str, usize, &[&str])] = &[
+    ("unary functions", 1, patterns::BUILTIN_UNARY_FUNCTIONS),
+    ("binary functions", 2, patterns::BUILTIN_BINARY_FUNCTIONS),
+    ("unary operators", 1, patterns::UNARY_OPERATORS),
+    ("binary operators", 2, patterns::BINARY_OPERATORS),
+    ("variables", 0, patterns::BUILTIN_VARIABLE_IDENTIFIERS),
+];
+
+pub struct Session {
+    pub parser: Parser,
+    pub evaluator: Evaluator,
+    history: VecDeque<EnvironmentSnapshot>,
+}
+
+impl Session {
+    const HISTORY_LIMIT: usize = 100;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn eval<S: AsRef<str>>(&mut self, input: S) -> Result<Outcome, TCalcError> {
+        let input = input.as_ref();
+        self._eval_at(input, 0, 0)
+            .map_err(|e| e.with_source_context(input))
+    }
+
+    pub fn eval_script<S: AsRef<str>>(&mut self, src: S) -> Vec<Result<Outcome, TCalcError>> {
+        // A failing statement does not keep the ones after it from being evaluated
+        let mut results = Vec::new();
+        for (line, text) in normalize_line_endings(src.as_ref()).split('\n').enumerate() {
+            results.extend(self._eval_line(text, line));
+        }
+        results
+    }
+
+    pub fn eval_script_file<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+    ) -> std::io::Result<Vec<Result<Outcome, TCalcError>>> {
+        let src = std::fs::read_to_string(path)?;
+        Ok(self.eval_script(src))
+    }
+
+    fn _eval_line(&mut self, text: &str, line: usize) -> Vec<Result<Outcome, TCalcError>> {
+        // Statements on a line are separated by ';', and anything after '#' is a comment
+        let code = match text.find(patterns::COMMENT_INITIAL_CHAR) {
+            Some(index) => &text[..index],
+            None => text,
+        };
+        let mut results = Vec::new();
+        let mut chr: usize = 0;
+        for statement in code.split(patterns::STATEMENT_SEPARATOR_CHAR) {
+            if !statement.trim().is_empty() {
+                // The whole line is more useful context than the statement the parser saw
+                results.push(
+                    self._eval_at(statement, line, chr)
+                        .map_err(|e| e.with_source_context(text)),
+                );
+            }
+            chr += statement.chars().count() + 1;
+        }
+        results
+    }
+
+    fn _eval_at<S: AsRef<str>>(
+        &mut self,
+        input: S,
+        line: usize,
+        chr: usize,
+    ) -> Result<Outcome, TCalcError> {
+        let input = input.as_ref();
+        if let Some(outcome) = self._run_command(input, line, chr) {
+            return outcome;
+        }
+        let mut ast = self
+            .parser
+            .parse(input, line, chr, &self.evaluator.environment.functions)?;
+        // Only a statement that went through can be undone, so its snapshot is kept until then
+        let snapshot = ast
+            .iter()
+            .any(Self::_contains_assignment)
+            .then(|| self.evaluator.environment.snapshot());
+        if ast.len() == 1
+            && let Some(call) = Self::_text_call(&mut ast[0])
+        {
+            let outcome = self._eval_text_call(call)?;
+            if let Some(snapshot) = snapshot {
+                self._push_history(snapshot);
+            }
+            return Ok(outcome);
+        }
+        let assigned_name = ast.last().and_then(Self::_assigned_name);
+        let value = self.evaluator.evaluate_value(ast)?;
+        if let Some(snapshot) = snapshot {
+            self._push_history(snapshot);
+        }
+        self.evaluator.environment.remember_result(value.clone());
+        // Results are kept at full precision, only the one shown is rounded to `\precision`
+        let value = value.round_significant(self.evaluator.environment.settings.precision);
+        // `\casefold` changes how the parser reads the statements that follow
+        if let Some(name) = &assigned_name
+            && name.starts_with(patterns::IDENTIFIER_PREFIX_CHAR)
+        {
+            self.parser.casefold = self.evaluator.environment.settings.casefold;
+        }
+        // Results other than in base 10 are only shown as text, in `\outbase`
+        let settings = &self.evaluator.environment.settings;
+        if settings.outbase != 10 {
+            let numeral = value.to_numeral_string_in_base(settings.outbase, settings.precision);
+            return Ok(Outcome::Text(match assigned_name {
+                Some(name) => format!("{name} = {numeral}"),
+                None => numeral,
+            }));
+        }
+        Ok(match assigned_name {
+            Some(name) => Outcome::Assignment { name, value },
+            None => Outcome::Value(value),
+        })
+    }
+
+    fn _run_command(
+        &mut self,
+        statement: &str,
+        line: usize,
+        chr: usize,
+    ) -> Option<Result<Outcome, TCalcError>> {
+        // None if the statement is not a command and should be parsed as an expression
+        let mut words = statement.split_whitespace();
+        let command = words
+            .next()
+            .filter(|word| patterns::SESSION_COMMANDS.contains(word))?;
+        let arguments: Vec<&str> = words.collect();
+        let indent = statement.chars().take_while(|c| c.is_whitespace()).count();
+        let position = InputPosition::new("unknown", line, chr + indent);
+        Some(match (command, arguments.as_slice()) {
+            ("vars", []) => Ok(Outcome::Command(Command::Vars(self._user_variables()))),
+            ("del", [name]) => self._delete_variable(name, position),
+            ("settings", []) => Ok(Outcome::Command(Command::Settings(
+                self.evaluator.environment.settings_summary(),
+            ))),
+            ("builtins", []) => Ok(Outcome::Command(Command::Builtins(
+                BUILTIN_CATEGORIES
+                    .iter()
+                    .map(|(category, _, names)| {
+                        (
+                            category.to_string(),
+                            names.iter().map(|n| n.to_string()).collect(),
+                        )
+                    })
+                    .collect(),
+            ))),
+            ("builtins", [name]) => Self::_describe_builtin(name, position),
+            ("angle", []) => Ok(Outcome::Command(Command::Angle(
+                self.evaluator.environment.angle_unit,
+            ))),
+            ("angle", ["deg"]) => Ok(self._set_angle_unit(AngleUnit::Degrees)),
+            ("angle", ["rad"]) => Ok(self._set_angle_unit(AngleUnit::Radians)),
+            ("reset", []) => {
+                self.reset();
+                Ok(Outcome::Command(Command::Reset))
+            }
+            _ => Err(SyntaxError::newp(
+                format!("Invalid arguments to the \"{command}\" command"),
+                position,
+            )
+            .into()),
+        })
+    }
+
+    fn _text_call(node: &mut AstNode) -> Option<&mut AstNode> {
+        // The call to a text function a statement consists of, looking through parentheses
+        match node.token.type_ {
+            TokenType::Expression if node.subtree.len() == 1 => {
+                Self::_text_call(&mut node.subtree[0])
+            }
+            type_
+                if type_.is_function_identifier()
+                    && patterns::TEXT_FUNCTIONS
+                        .contains(&node.token.content_to_string().as_str()) =>
+            {
+                Some(node)
+            }
+            _ => None,
+        }
+    }
+
+    fn _eval_text_call(&mut self, call: &mut AstNode) -> Result<Outcome, TCalcError> {
+        let mut operands = std::mem::take(&mut call.subtree);
+        self.evaluator.evaluate(&mut operands)?;
+        let values: Option<Vec<&Value>> = operands.iter().map(|node| node.value.as_ref()).collect();
+        let text = match (call.token.content_to_string().as_str(), values.as_deref()) {
+            ("tobase", Some([base, value])) => value.to_string_base(base),
+            ("factors", Some([value])) => value.factors(),
+            _ => {
+                return Err(SyntaxError::newp(
+                    "Expression did not evaluate to a value",
+                    call.token.position.clone(),
+                )
+                .with_span(call.token.content.len())
+                .into());
+            }
+        };
+        Ok(Outcome::Text(unwrap_or_propagate!(
+            text,
+            position: call.token.position.clone()
+        )))
+    }
+
+    fn _describe_builtin(name: &str, position: InputPosition) -> Result<Outcome, TCalcError> {
+        let descriptions: Vec<String> = BUILTIN_CATEGORIES
+            .iter()
+            .filter(|(_, _, names)| names.contains(&name))
+            .map(|(category, arity, _)| match arity {
+                0 => format!("built-in {}", category.trim_end_matches('s')),
+                1 => format!("{} taking 1 argument", category.trim_end_matches('s')),
+                _ => format!(
+                    "{} taking {arity} arguments",
+                    category.trim_end_matches('s')
+                ),
+            })
+            .collect();
+        if descriptions.is_empty() {
+            return Err(
+                SyntaxError::newp(format!("\"{name}\" is not a built-in"), position).into(),
+            );
+        }
+        Ok(Outcome::Command(Command::Builtin {
+            name: name.to_string(),
+            descriptions,
+        }))
+    }
+
+    fn _user_variables(&self) -> Vec<(String, Value)> {
+        // Settings such as `\precision` and protected constants are left out
+        let variables = &self.evaluator.environment.variables;
+        let mut user_variables: Vec<(String, Value)> = variables
+            .map
+            .iter()
+            .filter(|(identifier, _)| {
+                !variables.is_protected(identifier)
+                    && !identifier.starts_with(patterns::IDENTIFIER_PREFIX_CHAR)
+            })
+            .map(|(identifier, value)| (identifier.clone(), value.clone()))
+            .collect();
+        user_variables.sort_by(|a, b| a.0.cmp(&b.0));
+        user_variables
+    }
+
+    fn _set_angle_unit(&mut self, unit: AngleUnit) -> Outcome {
+        // Like an assignment, switching the unit can be undone
+        let snapshot = self.evaluator.environment.snapshot();
+        self.evaluator.environment.angle_unit = unit;
+        self._push_history(snapshot);
+        Outcome::Command(Command::Angle(unit))
+    }
+
+    fn _delete_variable(
+        &mut self,
+        identifier: &str,
+        position: InputPosition,
+    ) -> Result<Outcome, TCalcError> {
+        let variables = &self.evaluator.environment.variables;
+        if variables.is_readonly(identifier) {
+            return Err(InvalidOperationError::newp(
+                format!("The variable \"{identifier}\" is readonly"),
+                position,
+            )
+            .into());
+        }
+        if !variables.contains(identifier) {
+            return Err(SyntaxError::newp(
+                format!("The variable \"{identifier}\" is undefined"),
+                position,
+            )
+            .into());
+        }
+        // Deleting a variable can be undone just like assigning to it
+        let snapshot = self.evaluator.environment.snapshot();
+        self.evaluator.environment.variables.remove(identifier);
+        self._push_history(snapshot);
+        self.evaluator.clear_memo();
+        Ok(Outcome::Command(Command::Del(identifier.to_string())))
+    }
+
+    pub fn reset(&mut self) {
+        // Back to the state of a new session, except for the functions registered by the host
+        let functions = std::mem::take(&mut self.evaluator.environment.functions);
+        self.evaluator.environment = Environment {
+            functions,
+            ..Environment::default()
+        };
+        self.evaluator.clear_memo();
+        self.parser.casefold = self.evaluator.environment.settings.casefold;
+        self.history.clear();
+    }
+
+    pub fn undo(&mut self) -> bool {
+        // Reverts the most recent statement that assigned to a variable or a setting
+        match self.history.pop_back() {
+            Some(snapshot) => {
+                self.evaluator.environment.restore(snapshot);
+                self.evaluator.clear_memo();
+                self.parser.casefold = self.evaluator.environment.settings.casefold;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn _push_history(&mut self, snapshot: EnvironmentSnapshot) {
+        if self.history.len() == Self::HISTORY_LIMIT {
+            self.history.pop_front();
+        }
+        self.history.push_back(snapshot);
+    }
+
+    fn _assigned_name(node: &AstNode) -> Option<String> {
+        // The variable a top-level assignment writes to, such as `x` in `x := 5`
+        let is_assignment = node.token.type_ == TokenType::BinaryOperator
+            && patterns::ASSIGNMENT_OPERATORS.contains(&node.token.content_to_string().as_str());
+        match node.subtree.first() {
+            Some(target) if is_assignment && target.token.type_.is_variable_identifier() => {
+                Some(target.token.content_to_string())
+            }
+            _ => None,
+        }
+    }
+
+    fn _contains_assignment(node: &AstNode) -> bool {
+        (node.token.type_ == TokenType::BinaryOperator
+            && patterns::ASSIGNMENT_OPERATORS.contains(&node.token.content_to_string().as_str()))
+            || node.subtree.iter().any(Self::_contains_assignment)
+    }
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Self {
+            parser: Parser::new(),
+            evaluator: Evaluator::new(),
+            history: VecDeque::with_capacity(Self::HISTORY_LIMIT),
+        }
+    }
+}
+
+pub fn repl<R: BufRead, W: Write>(reader: R, mut writer: W) -> std::io::Result<()> {
+    // Evaluates each line read in a single persistent session until EOF
+    let mut session = Session::new();
+    let mut line: usize = 0;
+    for text in reader.lines() {
+        // A lone '\r' is not a line break to `BufRead::lines`, so split on it here
+        for text in normalize_line_endings(&text?).split('\n') {
+            for result in session._eval_line(text, line) {
+                match result {
+                    Ok(outcome) => writeln!(writer, "{outcome}")?,
+                    Err(e) => writeln!(writer, "{e}")?,
+                }
+            }
+            for warning in session.evaluator.take_warnings() {
+                writeln!(writer, "{warning}")?;
+            }
+            line += 1;
+        }
+        writer.flush()?;
+    }
+    Ok(())
+}
+
+impl Display for Outcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Value(value) => write!(f, "{value}"),
+            Self::Assignment { name, value } => write!(f, "{name} = {value}"),
+            Self::Command(command) => write!(f, "{command}"),
+            Self::Text(text) => write!(f, "{text}"),
+        }
+    }
+}
+
+impl Display for Command {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Vars(variables) => write!(f, "{}", name_value_lines(variables)),
+            Self::Del(identifier) => write!(f, "Deleted {identifier}"),
+            Self::Reset => write!(f, "Reset to the default environment"),
+            Self::Settings(settings) => write!(f, "{}", name_value_lines(settings)),
+            Self::Builtins(categories) => {
+                let lines: Vec<String> = categories
+                    .iter()
+                    .map(|(category, names)| format!("{category}: {}", names.join(" ")))
+                    .collect();
+                write!(f, "{}", lines.join("\n"))
+            }
+            Self::Builtin { name, descriptions } => {
+                write!(f, "{name}: {}", descriptions.join("; "))
+            }
+            Self::Angle(AngleUnit::Degrees) => write!(f, "Angles are in degrees"),
+            Self::Angle(AngleUnit::Radians) => write!(f, "Angles are in radians"),
+        }
+    }
+}
+
+fn name_value_lines<V: Display>(pairs: &[(String, V)]) -> String {
+    pairs
+        .iter()
+        .map(|(name, value)| format!("{name} = {value}"))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+fn normalize_line_endings(src: &str) -> String {
+    // Windows ("\r\n") and classic Mac ("\r") line endings both become "\n"
+    src.replace("\r\n", "\n").replace('\r', "\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use super::*;
+    use crate::core::functions::Function;
+    use crate::core::integers::Integer;
+    use crate::core::settings::Settings;
+
+    fn eval_all(session: &mut Session, inputs: &[&str]) -> Vec<String> {
+        inputs
+            .iter()
+            .map(|input| match session.eval(input) {
+                Ok(outcome) => outcome.to_string(),
+                Err(e) => e.to_string(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn signed_arguments_do_not_continue_the_previous_result() {
+        let mut session = Session::new();
+        let outcomes = eval_all(&mut session, &["2 + 3", "tobase(2, -5)", "log(2, -1 + 9)"]);
+        assert_eq!(outcomes[1], "-0b101");
+        assert_eq!(outcomes[2], "Value(Decimal: 3.0)");
+    }
+
+    #[test]
+    fn signed_list_elements_do_not_continue_the_previous_result() {
+        let mut session = Session::new();
+        let outcomes = eval_all(&mut session, &["2 + 3", "mean([-1, 3])", "sum([-1, 3])"]);
+        assert_eq!(outcomes[1], "Value(Decimal: 1.0)");
+        assert_eq!(outcomes[2], "Value(Integer: 2)");
+    }
+
+    #[test]
+    fn missing_operand_errors_show_their_position_once() {
+        let mut session = Session::new();
+        let outcomes = eval_all(&mut session, &["sqrt", "2 3 cat"]);
+        assert_eq!(
+            outcomes[0],
+            "\"Syntax Error\": Unary function 'sqrt' is missing a right-hand operand at unknown:1:1"
+        );
+        assert_eq!(
+            outcomes[1],
+            "\"Syntax Error\": Binary function 'cat' is missing a right-hand operand after '3' at unknown:1:5"
+        );
+    }
+
+    #[test]
+    fn failed_assignments_are_not_undone() {
+        let mut session = Session::new();
+        eval_all(&mut session, &["x := 1", "x := 2", "x := 1/0"]);
+        assert!(session.undo());
+        assert_eq!(eval_all(&mut session, &["x"]), ["Value(Integer: 1)"]);
+    }
+
+    #[test]
+    fn precision_and_inbase_settings_take_effect() {
+        let mut session = Session::new();
+        let outcomes = eval_all(
+            &mut session,
+            &[
+                "\\precision := 5",
+                "2 / 3",
+                "mem 0 * 3",
+                "\\inbase := 8",
+                "17",
+                "0d17",
+            ],
+        );
+        assert_eq!(outcomes[1], "Value(Decimal: 0.66667)");
+        assert_eq!(outcomes[2], "Value(Decimal: 2.0)");
+        assert_eq!(outcomes[4], "Value(Integer: 15)");
+        assert_eq!(outcomes[5], "Value(Integer: 17)");
+    }
+
+    #[test]
+    fn commas_in_calls_always_separate_arguments() {
+        let mut session = Session::new();
+        let outcomes = eval_all(&mut session, &["log(2,8)", "max(3,1,2)", "(3,5)"]);
+        assert_eq!(outcomes[0], "Value(Decimal: 3.0)");
+        assert_eq!(outcomes[1], "Value(Integer: 3)");
+        assert_eq!(outcomes[2], "Value(Decimal: 3.5)");
+    }
+
+    #[test]
+    fn identity_operations_on_bitseqs_still_give_integers() {
+        let mut session = Session::new();
+        let outcomes = eval_all(&mut session, &["b := 0b101", "b + 0", "b * 1", "0b101 - 0"]);
+        assert_eq!(outcomes[1], "Value(Integer: 5)");
+        assert_eq!(outcomes[2], "Value(Integer: 5)");
+        assert_eq!(outcomes[3], "Value(Integer: 5)");
+    }
+
+    #[test]
+    fn folding_constants_keeps_their_warnings() {
+        let mut session = Session::new();
+        eval_all(&mut session, &["0b0101 + 1"]);
+        assert_eq!(session.evaluator.take_warnings().len(), 1);
+    }
+
+    #[test]
+    fn memoized_results_repeat_their_warnings() {
+        let mut session = Session::new();
+        session.evaluator.memoize = true;
+        eval_all(&mut session, &["b := 0b0101"]);
+        for _ in 0..2 {
+            eval_all(&mut session, &["(b + 1) * 2"]);
+            assert_eq!(session.evaluator.take_warnings().len(), 1);
+        }
+    }
+
+    #[test]
+    fn parallel_evaluation_sees_earlier_assignments() {
+        let mut session = Session::new();
+        session.evaluator.parallel = true;
+        let outcomes = eval_all(
+            &mut session,
+            &[
+                "x := 1",
+                "((x + 1) * (x + 2)) + (x := 3) + ((x + 1) * (x + 2))",
+            ],
+        );
+        assert_eq!(outcomes[1], "Value(Integer: 29)");
+    }
+
+    #[test]
+    fn folded_constants_draw_on_the_step_budget() {
+        let mut session = Session::new();
+        session.evaluator.step_budget = Some(5);
+        let outcomes = eval_all(&mut session, &["2 ^ 0.5", "x := 2", "x ^ 0.5"]);
+        assert!(outcomes[0].contains("Computation budget exceeded"));
+        assert!(outcomes[2].contains("Computation budget exceeded"));
+    }
+
+    #[test]
+    fn a_tiny_step_budget_aborts_exp_of_a_large_argument() {
+        let mut session = Session::new();
+        session.evaluator.step_budget = Some(5);
+        // A power is folded into a constant, by way of exp(100.5 * ln(10)), before evaluation
+        let outcomes = eval_all(&mut session, &["x := 100", "exp(x)", "10 ^ 100.5"]);
+        assert!(outcomes[1].contains("Computation budget exceeded"));
+        assert!(outcomes[2].contains("Computation budget exceeded"));
+        session.evaluator.step_budget = Some(100_000);
+        let outcomes = eval_all(&mut session, &["exp(x)", "10 ^ 100.5"]);
+        assert!(
+            outcomes[0]
+                .starts_with("Value(Decimal: 26881171418161354484126255515800135873611118.77")
+        );
+        assert!(outcomes[1].starts_with("Value(Decimal: 31622776601683793319"));
+    }
+
+    #[test]
+    fn errors_carry_the_statement_as_context() {
+        let mut session = Session::new();
+        let error = session.eval("2 * (3 +)").err().unwrap();
+        assert_eq!(error.source_context(), Some("2 * (3 +)"));
+    }
+
+    #[test]
+    fn text_call_errors_point_at_the_call() {
+        let mut session = Session::new();
+        let error = session.eval("  tobase(2)").err().unwrap();
+        assert!(error.to_string().ends_with("at unknown:1:3"));
+    }
+
+    struct Triple;
+
+    impl Function for Triple {
+        fn name(&self) -> &str {
+            "triple"
+        }
+
+        fn arity(&self) -> usize {
+            1
+        }
+
+        fn call(&self, args: &[Value]) -> Result<Value, TCalcError> {
+            Ok(args[0].mul(&Value::from(Integer::from(3u32)))?)
+        }
+    }
+
+    #[test]
+    fn functions_registered_with_the_environment_can_be_called() {
+        let mut session = Session::new();
+        let functions = &mut session.evaluator.environment.functions;
+        assert!(functions.register(Rc::new(Triple)));
+        let outcomes = eval_all(&mut session, &["triple(4)", "1 + triple 2"]);
+        assert_eq!(outcomes[0], "Value(Integer: 12)");
+        assert_eq!(outcomes[1], "Value(Integer: 7)");
+    }
+
+    #[test]
+    fn switching_the_angle_unit_changes_how_sin_reads_its_argument() {
+        let mut session = Session::new();
+        let outcomes = eval_all(
+            &mut session,
+            &[
+                "sin 90",
+                "angle rad",
+                "sin 90",
+                "sin(pi / 2)",
+                "angle deg",
+                "angle",
+            ],
+        );
+        assert_eq!(outcomes[0], "Value(Decimal: 1.0)");
+        assert_eq!(outcomes[1], "Angles are in radians");
+        assert!(outcomes[2].starts_with("Value(Decimal: 0.89399666360055789051"));
+        assert_eq!(outcomes[3], "Value(Decimal: 1.0)");
+        assert_eq!(outcomes[5], "Angles are in degrees");
+    }
+
+    #[test]
+    fn trigonometric_functions_keep_tiny_results() {
+        let mut session = Session::new();
+        let outcomes = eval_all(
+            &mut session,
+            &["sin(1e-120)", "tan(1e-120)", "asin(1e-120)", "deg(1e-120)"],
+        );
+        assert!(outcomes[0].starts_with("Value(Decimal: 1.7453292519943295769"));
+        assert!(outcomes[0].ends_with("e-122)"));
+        assert_eq!(outcomes[1], outcomes[0]);
+        assert!(outcomes[2].starts_with("Value(Decimal: 5.7295779513082320876"));
+        assert!(outcomes[2].ends_with("e-119)"));
+        assert_eq!(outcomes[3], outcomes[2]);
+    }
+
+    #[test]
+    fn undo_restores_settings_and_the_angle_unit() {
+        let mut session = Session::new();
+        eval_all(
+            &mut session,
+            &["\\precision := 5", "\\casefold := 1", "angle rad"],
+        );
+        assert!(session.undo());
+        assert_eq!(session.evaluator.environment.angle_unit, AngleUnit::Degrees);
+        assert!(session.undo());
+        assert!(!session.parser.casefold);
+        assert!(session.undo());
+        assert_eq!(session.evaluator.environment.settings, Settings::default());
+        let outcomes = eval_all(&mut session, &["1 / 3"]);
+        assert!(outcomes[0].starts_with("Value(Decimal: 0.33333333333333333333"));
+    }
+
+    #[test]
+    fn tobase_writes_whole_numbers_in_bases_up_to_36() {
+        let mut session = Session::new();
+        let outcomes = eval_all(
+            &mut session,
+            &[
+                "tobase(36, 35)",
+                "tobase(2, 10)",
+                "tobase(3, 15)",
+                "tobase(16, 255)",
+                "tobase(1, 10)",
+                "tobase(37, 10)",
+                "tobase(10, 2.5)",
+            ],
+        );
+        assert_eq!(outcomes[..4], ["Z", "0b1010", "120", "0xff"]);
+        for outcome in &outcomes[4..6] {
+            assert!(
+                outcome.contains("The base must be a whole number from 2 to 36"),
+                "{outcome}"
+            );
+        }
+        assert!(outcomes[6].contains("Error"), "{}", outcomes[6]);
+    }
+
+    #[test]
+    fn results_are_shown_in_the_output_base() {
+        let mut session = Session::new();
+        let outcomes = eval_all(
+            &mut session,
+            &[
+                "\\outbase := 2",
+                "5",
+                "0 - 2.75",
+                "x := 0b0101",
+                "\\precision := 5",
+                "1 / 3",
+                "\\outbase := 0b10000",
+                "255 + 0.5",
+                "\\outbase := 0x24",
+                "35",
+            ],
+        );
+        assert_eq!(outcomes[0], "\\outbase = 0b10");
+        assert_eq!(outcomes[1], "0b101");
+        assert_eq!(outcomes[2], "-0b10.11");
+        assert_eq!(outcomes[3], "x = 0b0101");
+        assert_eq!(outcomes[5], "0b0.0101010101010101");
+        assert_eq!(outcomes[7], "0xff.8");
+        assert_eq!(outcomes[9], "Z");
+    }
+
+    #[test]
+    fn memoized_subexpressions_are_computed_once() {
+        // The budget covers one exp(1.5) but not two
+        for memoize in [false, true] {
+            let mut session = Session::new();
+            session.evaluator.memoize = memoize;
+            session.evaluator.step_budget = Some(150);
+            let outcomes = eval_all(&mut session, &["exp(1.5) * exp(1.5)"]);
+            assert_eq!(
+                outcomes[0].contains("Computation budget exceeded"),
+                !memoize
+            );
+        }
+    }
+
+    // A benchmark rather than a test: cargo test --release -- --ignored --nocapture
+    #[test]
+    #[ignore]
+    fn benchmark_memoizing_a_repeated_subterm() {
+        let input = vec!["exp(1.5) ^ 0.3"; 20].join(" + ");
+        for memoize in [false, true] {
+            let mut session = Session::new();
+            session.evaluator.memoize = memoize;
+            let start = std::time::Instant::now();
+            eval_all(&mut session, &[input.as_str()]);
+            println!("memoize = {memoize}: {:?}", start.elapsed());
+        }
+    }
+}
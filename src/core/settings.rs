@@ -0,0 +1,141 @@
+use std::ops::RangeInclusive;
+
+use crate::core::bitseqs::Bitseq;
+use crate::core::errors::InvalidOperationError;
+use crate::core::integers::Integer;
+use crate::core::values::Value;
+
+// Read as `\name` and changed with `\name := value`, each assignment being validated.
+// `\inbase` applies to numerals without a base prefix, `\outbase` to the base a result is
+// shown in and `\precision` to the significant digits it is shown with; there is no
+// `\showfracs` until there is a fraction type.
+// Like any other numeral, the value assigned to `\inbase` is read in the current base, so
+// `\inbase := 0d10` is how to get back to decimal
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Settings {
+    pub inbase: u32,
+    pub outbase: u32,
+    pub precision: u32,
+    pub casefold: bool,
+}
+
+impl Settings {
+    pub const NAMES: &[&str] = &["\\inbase", "\\outbase", "\\precision", "\\casefold"];
+    // Significant digits of the Decimal type
+    pub const MAX_PRECISION: u32 = 154;
+    const BASES: RangeInclusive<u32> = 2..=36;
+
+    pub fn get<S: AsRef<str>>(&self, identifier: S) -> Option<Value> {
+        let value = match identifier.as_ref().to_lowercase().as_str() {
+            "\\inbase" => Value::from(Integer::from(self.inbase)),
+            "\\outbase" => Value::from(Integer::from(self.outbase)),
+            "\\precision" => Value::from(Integer::from(self.precision)),
+            "\\casefold" => Self::_flag_value(self.casefold),
+            _ => return None,
+        };
+        Some(value)
+    }
+
+    pub fn set<S: AsRef<str>>(
+        &mut self,
+        identifier: S,
+        value: &Value,
+    ) -> Result<(), InvalidOperationError> {
+        let identifier = identifier.as_ref().to_lowercase();
+        match identifier.as_str() {
+            "\\inbase" => self.inbase = Self::_whole_number_in(&identifier, value, Self::BASES)?,
+            "\\outbase" => self.outbase = Self::_whole_number_in(&identifier, value, Self::BASES)?,
+            "\\precision" => {
+                self.precision =
+                    Self::_whole_number_in(&identifier, value, 1..=Self::MAX_PRECISION)?
+            }
+            "\\casefold" => self.casefold = Self::_whole_number_in(&identifier, value, 0..=1)? == 1,
+            _ => {
+                return Err(InvalidOperationError::new(format!(
+                    "The setting \"{identifier}\" is undefined"
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    fn _flag_value(flag: bool) -> Value {
+        Value::from(if flag { Bitseq::ONE } else { Bitseq::ZERO })
+    }
+
+    fn _whole_number_in(
+        identifier: &str,
+        value: &Value,
+        range: RangeInclusive<u32>,
+    ) -> Result<u32, InvalidOperationError> {
+        let number: Option<Integer> = value.clone().try_into().ok();
+        number
+            .and_then(|number| range.clone().find(|n| Integer::from(*n) == number))
+            .ok_or_else(|| {
+                InvalidOperationError::new(format!(
+                    "The setting \"{identifier}\" must be a whole number from {} to {}, not {}",
+                    range.start(),
+                    range.end(),
+                    value.to_numeral_string()
+                ))
+            })
+    }
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            inbase: 10,
+            outbase: 10,
+            precision: Self::MAX_PRECISION,
+            casefold: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn settings_read_back_what_was_set() {
+        let mut settings = Settings::default();
+        for (name, number) in [
+            ("\\inbase", 16u32),
+            ("\\outbase", 2),
+            ("\\precision", 5),
+            ("\\casefold", 1),
+        ] {
+            settings
+                .set(name, &Value::from(Integer::from(number)))
+                .unwrap();
+            let value: Integer = settings.get(name).unwrap().try_into().unwrap();
+            assert_eq!(value, Integer::from(number), "{name}");
+        }
+    }
+
+    #[test]
+    fn settings_reject_values_out_of_range() {
+        let mut settings = Settings::default();
+        for (name, number) in [
+            ("\\inbase", 1u32),
+            ("\\outbase", 37),
+            ("\\precision", 0),
+            ("\\precision", Settings::MAX_PRECISION + 1),
+            ("\\casefold", 2),
+        ] {
+            assert!(
+                settings
+                    .set(name, &Value::from(Integer::from(number)))
+                    .is_err(),
+                "{name} := {number}"
+            );
+        }
+        assert!(
+            settings
+                .set("\\showfracs", &Value::from(Bitseq::ONE))
+                .is_err()
+        );
+        assert_eq!(settings, Settings::default());
+    }
+}
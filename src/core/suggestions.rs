@@ -0,0 +1,36 @@
+use crate::core::patterns;
+
+fn levenshtein_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current: Vec<usize> = vec![0; b.len() + 1];
+    for i in 1..=a.len() {
+        current[0] = i;
+        for j in 1..=b.len() {
+            let substitution_cost = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+            current[j] = (previous[j] + 1)
+                .min(current[j - 1] + 1)
+                .min(previous[j - 1] + substitution_cost);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()]
+}
+
+pub fn closest_builtin_function<S: AsRef<str>>(identifier: S) -> Option<&'static str> {
+    // Short names tolerate a single typo, longer ones two
+    let identifier = identifier.as_ref();
+    let max_distance = if identifier.chars().count() <= 3 {
+        1
+    } else {
+        2
+    };
+    patterns::BUILTIN_UNARY_FUNCTIONS
+        .iter()
+        .chain(patterns::BUILTIN_BINARY_FUNCTIONS.iter())
+        .map(|name| (*name, levenshtein_distance(identifier, name)))
+        .filter(|(_, distance)| *distance <= max_distance)
+        .min_by_key(|(_, distance)| *distance)
+        .map(|(name, _)| name)
+}
@@ -20,6 +20,7 @@ pub enum TokenType {
     Decimal,
     Expression,
     Integer,
+    List,
     UnaryFunctionIdentifier,
     UnaryOperator,
     VariableIdentifier,
@@ -91,6 +92,7 @@ impl Display for TokenType {
                 Self::Decimal => "Decimal",
                 Self::Expression => "Expression",
                 Self::Integer => "Integer",
+                Self::List => "List",
                 Self::UnaryFunctionIdentifier => "UnaryFunctionIdentifier",
                 Self::UnaryOperator => "UnaryOperator",
                 Self::VariableIdentifier => "VariableIdentifier",
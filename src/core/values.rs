@@ -1,10 +1,15 @@
 use std::collections::{HashMap, HashSet};
 use std::convert::From;
-use std::fmt::Display;
+use std::fmt::{Binary, Display, LowerHex, Octal};
+use std::ops::{Add, Div, Mul, Sub};
+use std::str::FromStr;
+
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 use crate::core::bitseqs::Bitseq;
 use crate::core::decimals::{AngleUnit, Decimal};
-use crate::core::errors::{ConversionError, InvalidOperationError, SyntaxError};
+use crate::core::errors::{ConversionError, InvalidOperationError, SyntaxError, TCalcError};
 use crate::core::integers::Integer;
 use crate::core::patterns;
 
@@ -29,12 +34,17 @@ impl Display for ValueType {
     }
 }
 
+// Only the representation in use is stored, so cloning a Value copies just that
+#[derive(Clone, Copy)]
+enum ValueInner {
+    Bitseq(Bitseq),
+    Decimal(Decimal),
+    Integer(Integer),
+}
+
 #[derive(Clone)]
 pub struct Value {
-    type_: ValueType,
-    val_bitseq: Bitseq,
-    val_decimal: Decimal,
-    val_integer: Integer,
+    inner: ValueInner,
 }
 
 impl Value {
@@ -44,7 +54,10 @@ impl Value {
             Some(2)
         } else if patterns::OCTAL_INTEGER.is_match(s) || patterns::OCTAL_DECIMAL.is_match(s) {
             Some(8)
-        } else if patterns::DECIMAL_INTEGER.is_match(s) || patterns::DECIMAL_DECIMAL.is_match(s) {
+        } else if patterns::DECIMAL_INTEGER.is_match(s)
+            || patterns::DECIMAL_DECIMAL.is_match(s)
+            || patterns::DECIMAL_SCIENTIFIC.is_match(s)
+        {
             Some(10)
         } else if patterns::HEXADECIMAL_INTEGER.is_match(s)
             || patterns::HEXADECIMAL_DECIMAL.is_match(s)
@@ -65,24 +78,58 @@ impl Value {
         patterns::BASE_PREFIX.is_match(s)
     }
 
-    fn _strip_base_prefix<S: AsRef<str>>(s: S) -> String {
-        let s = s.as_ref().to_string();
-        match s.get(2..) {
-            Some(subs) => subs.to_string(),
-            None => s,
+    fn _base_of_prefix<S: AsRef<str>>(s: S) -> Option<(u32, &'static str)> {
+        match s.as_ref().chars().nth(1) {
+            Some('b' | 'B') => Some((2, "binary")),
+            Some('o' | 'O') => Some((8, "octal")),
+            Some('d' | 'D') => Some((10, "decimal")),
+            Some('x' | 'X') => Some((16, "hexadecimal")),
+            _ => None,
         }
     }
 
-    fn _strip_str<S: AsRef<str>>(s: S) -> String {
-        let s = s.as_ref().to_string();
-        let result = s.replace('_', "").replace(',', ".");
-        if Self::_has_base_prefix(s) {
-            Self::_strip_base_prefix(result)
+    fn _invalid_prefixed_digit_error<S: AsRef<str>>(s: S) -> Option<SyntaxError> {
+        // The prefix fixes the base, so name the first digit that does not belong to it
+        let s = s.as_ref();
+        if !Self::_has_base_prefix(s) {
+            return None;
+        }
+        let (base, base_name) = Self::_base_of_prefix(s)?;
+        let invalid_digit = Self::_strip_base_prefix(s)
+            .chars()
+            .find(|c| !"_.,".contains(*c) && c.to_digit(base).is_none())?;
+        Some(SyntaxError::new(format!(
+            "'{}' is not a valid {} digit in the numeral \"{}\"",
+            invalid_digit, base_name, s
+        )))
+    }
+
+    fn _separator_after_prefix_error<S: AsRef<str>>(s: S) -> Option<SyntaxError> {
+        // Digit separators group digits, so one cannot come before the first digit
+        let s = s.as_ref();
+        if Self::_has_base_prefix(s) && Self::_strip_base_prefix(s).starts_with('_') {
+            Some(SyntaxError::new(format!(
+                "A digit separator cannot directly follow the base prefix in the numeral \"{}\"",
+                s
+            )))
         } else {
-            result
+            None
         }
     }
 
+    fn _strip_base_prefix<S: AsRef<str>>(s: S) -> String {
+        // Only removes an actual prefix, so unprefixed numerals keep all their digits
+        patterns::BASE_PREFIX.replace(s.as_ref(), "").to_string()
+    }
+
+    fn _strip_str<S: AsRef<str>>(s: S) -> String {
+        // The prefix is stripped from the numeral as written, before separators are removed
+        // or normalised and could shift where it ends
+        Self::_strip_base_prefix(s)
+            .replace('_', "")
+            .replace(',', ".")
+    }
+
     fn _char_to_val(c: char) -> u8 {
         match c {
             '0'..='9' => (c as u8) - b'0',
@@ -134,6 +181,36 @@ impl Value {
         }
     }
 
+    pub fn bitseq_from_str(s: &str) -> Result<Self, SyntaxError> {
+        // A binary or hexadecimal integer numeral as a Bitseq as wide as it is written, so
+        // that 0x0F is the 8-bit 0b00001111 rather than the 4-bit 0b1111
+        let value = Self::from_str(s)?;
+        if value.value_type() == ValueType::Bitseq {
+            return Ok(value);
+        }
+        if Self::_check_str_and_get_base(s) == Some(16) && !Self::_has_fractional_separator(s) {
+            let norm_s = Self::_strip_str(s);
+            if let Some(b) = Bitseq::from_hex_str(&norm_s) {
+                return Ok(Self::from_bitseq(b));
+            }
+        }
+        Err(SyntaxError::new(format!(
+            "The numeral \"{}\" cannot be read as a bit-sequence value",
+            s
+        )))
+    }
+
+    fn _from_fixed_point_bitseq_str(s: &str) -> Result<Value, SyntaxError> {
+        let norm_s = Self::_strip_str(s);
+        match Bitseq::from_fixed_point_str(&norm_s) {
+            Some(b) => Ok(Self::from_bitseq(b)),
+            None => Err(SyntaxError::new(format!(
+                "Failed to parse string \"{}\" (normalised to \"{}\") into fixed-point bit-sequence value",
+                s, norm_s
+            ))),
+        }
+    }
+
     fn _from_int_str(s: &str, base: u8) -> Result<Self, SyntaxError> {
         let norm_s = Self::_strip_str(s);
         match Integer::from_str_radix(&norm_s, base.into()) {
@@ -157,107 +234,169 @@ impl Value {
         }
     }
 
-    pub fn from_str(s: &str) -> Result<Self, SyntaxError> {
-        let base: u8 = if let Some(b) = Self::_check_str_and_get_base(s) {
-            b
-        } else {
+    fn _from_scientific_str(s: &str) -> Result<Self, SyntaxError> {
+        // The exponent is always a decimal power of ten, so no base conversion applies
+        let norm_s = Self::_strip_str(s);
+        match norm_s.parse::<Decimal>() {
+            Ok(d) => Ok(Self::from_decimal(d)),
+            Err(_) => Err(SyntaxError::new(format!(
+                "Failed to parse string \"{}\" (normalised to \"{}\") into decimal value",
+                s, norm_s
+            ))),
+        }
+    }
+
+    pub fn from_str_in_base(s: &str, inbase: u32) -> Result<Self, SyntaxError> {
+        // Numerals without a base prefix are read in `inbase`, though only whole numbers with
+        // digits up to 9, as letters would be read as identifiers
+        if inbase == 10 || patterns::BASE_PREFIX.is_match(s) {
+            return Self::from_str(s);
+        }
+        if !patterns::DECIMAL_INTEGER.is_match(s) {
             return Err(SyntaxError::new(format!(
-                "The pattern of the numeral string \"{}\" is invalid",
-                s
+                "Only whole numbers can be written without a base prefix in base {inbase}, not \"{s}\""
             )));
-        };
-        if Self::_has_fractional_separator(s) {
-            Self::_from_dec_str(s, base)
-        } else if base == 2 {
-            Value::_from_bitseq_str(s)
-        } else {
-            Self::_from_int_str(s, base)
+        }
+        Integer::from_str_radix(Self::_strip_str(s), inbase).map(Self::from_integer)
+    }
+
+    pub fn round_significant(&self, digits: u32) -> Self {
+        // Only Decimals are rounded, as the other types are exact
+        match self.inner {
+            ValueInner::Decimal(d) => Self::from_decimal(d.round_significant(digits)),
+            _ => self.clone(),
         }
     }
 
     pub fn from_integer(i: Integer) -> Self {
         Self {
-            type_: ValueType::Integer,
-            val_integer: i,
-            val_decimal: Decimal::ZERO,
-            val_bitseq: Bitseq::ZERO,
+            inner: ValueInner::Integer(i),
         }
     }
 
     pub fn from_decimal(d: Decimal) -> Self {
         Self {
-            type_: ValueType::Decimal,
-            val_integer: Integer::ZERO,
-            val_decimal: d,
-            val_bitseq: Bitseq::ZERO,
+            inner: ValueInner::Decimal(d),
         }
     }
 
     pub fn from_bitseq(b: Bitseq) -> Self {
         Self {
-            type_: ValueType::Bitseq,
-            val_integer: Integer::ZERO,
-            val_decimal: Decimal::ZERO,
-            val_bitseq: b,
+            inner: ValueInner::Bitseq(b),
         }
     }
 
-    pub fn try_mutate_into(&mut self, into_type: ValueType) -> Result<(), ConversionError> {
-        if into_type == self.type_ {
-            return Ok(());
+    pub fn value_type(&self) -> ValueType {
+        match self.inner {
+            ValueInner::Bitseq(_) => ValueType::Bitseq,
+            ValueInner::Decimal(_) => ValueType::Decimal,
+            ValueInner::Integer(_) => ValueType::Integer,
         }
-        if self.type_ == ValueType::Bitseq {
-            if into_type == ValueType::Integer {
-                self.val_integer = self.val_bitseq.into();
-            }
-            if into_type == ValueType::Decimal {
-                self.val_decimal = self.val_bitseq.into();
-            }
-            self.val_bitseq = Bitseq::ZERO;
-            self.type_ = into_type;
-            return Ok(());
-        }
-        if self.type_ == ValueType::Integer {
-            if into_type == ValueType::Bitseq {
-                match Bitseq::try_from(self.val_integer.clone()) {
-                    Err(e) => return Err(e),
-                    Ok(converted) => {
-                        self.val_bitseq = converted;
-                    }
-                }
-            }
-            if into_type == ValueType::Decimal {
-                self.val_decimal = self.val_integer.into();
-            }
-            self.val_integer = Integer::ZERO;
-            self.type_ = into_type;
-            return Ok(());
-        }
-        if self.type_ == ValueType::Decimal {
-            if into_type == ValueType::Bitseq {
-                match Bitseq::try_from(self.val_decimal.clone()) {
-                    Err(e) => return Err(e),
-                    Ok(converted) => {
-                        self.val_bitseq = converted;
-                    }
-                }
-            }
-            if into_type == ValueType::Integer {
-                match Integer::try_from(self.val_decimal.clone()) {
-                    Err(e) => return Err(e),
-                    Ok(converted) => {
-                        self.val_integer = converted;
-                    }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        match self.inner {
+            ValueInner::Bitseq(b) => b.is_zero(),
+            ValueInner::Decimal(d) => d == Decimal::ZERO,
+            ValueInner::Integer(i) => i == Integer::ZERO,
+        }
+    }
+
+    pub fn is_integer(&self) -> bool {
+        // Whether the value is whole, whatever its type, so that 3.0 and 0b11.0 are as well
+        match self.inner {
+            ValueInner::Bitseq(b) => !b.has_fractional_part(),
+            ValueInner::Decimal(d) => d.fract() == Decimal::ZERO,
+            ValueInner::Integer(_) => true,
+        }
+    }
+
+    pub fn to_numeral_string(&self) -> String {
+        match self.inner {
+            ValueInner::Bitseq(b) => b.to_string(),
+            ValueInner::Integer(i) => i.to_string(),
+            ValueInner::Decimal(d) => d.to_string(),
+        }
+    }
+
+    fn _fmt_in_base(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+        radix: u32,
+        prefix: &str,
+    ) -> std::fmt::Result {
+        // Values with a fractional part have no digits in these bases here, so they are
+        // written as their numeral string instead
+        if let ValueInner::Bitseq(b) = self.inner
+            && radix == 2
+        {
+            return f.pad_integral(true, prefix, &b.to_digit_string());
+        }
+        match self._to_integer() {
+            Ok(i) => {
+                let digits = i.to_string_base(radix);
+                match digits.strip_prefix('-') {
+                    Some(magnitude) => f.pad_integral(false, prefix, magnitude),
+                    None => f.pad_integral(true, prefix, &digits),
                 }
             }
-            self.val_decimal = Decimal::ZERO;
-            self.type_ = into_type;
-            return Ok(());
+            Err(_) => f.pad(&self.to_numeral_string()),
         }
-        Err(ConversionError::new(format!(
-            "No known conversion path to mutate {} to {}",
-            self.type_, into_type
-        )))
+    }
+
+    fn _to_bitseq(&self) -> Result<Bitseq, ConversionError> {
+        match self.inner {
+            ValueInner::Bitseq(b) => Ok(b),
+            ValueInner::Decimal(d) => Bitseq::try_from(d),
+            ValueInner::Integer(i) => Bitseq::try_from(i),
+        }
+    }
+
+    fn _to_decimal(&self) -> Decimal {
+        match self.inner {
+            ValueInner::Bitseq(b) => b.into(),
+            ValueInner::Decimal(d) => d,
+            ValueInner::Integer(i) => i.into(),
+        }
+    }
+
+    fn _to_integer(&self) -> Result<Integer, ConversionError> {
+        match self.inner {
+            ValueInner::Bitseq(b) if b.has_fractional_part() => Err(ConversionError::new(
+                "Cannot convert fixed-point Bitseq with a fractional part to Integer",
+            )),
+            ValueInner::Bitseq(b) => Ok(b.into()),
+            ValueInner::Decimal(d) => Integer::try_from(d),
+            ValueInner::Integer(i) => Ok(i),
+        }
+    }
+
+    pub fn quotient_terminates_in_base(&self, divisor: &Self, base: u32) -> bool {
+        // Only the quotient of two whole numbers is known exactly, anything else is assumed
+        // to terminate
+        if self.value_type() == ValueType::Decimal || divisor.value_type() == ValueType::Decimal {
+            return true;
+        }
+        match (self._to_integer(), divisor._to_integer()) {
+            (Ok(dividend), Ok(divisor)) => dividend.quotient_terminates_in_base(&divisor, base),
+            _ => true,
+        }
+    }
+
+    pub fn try_mutate_into(&mut self, into_type: ValueType) -> Result<(), ConversionError> {
+        self.inner = match into_type {
+            ValueType::Bitseq => ValueInner::Bitseq(self._to_bitseq()?),
+            ValueType::Decimal => ValueInner::Decimal(self._to_decimal()),
+            ValueType::Integer => ValueInner::Integer(self._to_integer()?),
+        };
+        Ok(())
+    }
+
+    pub fn cast(&self, into_type: ValueType) -> Result<Self, ConversionError> {
+        // Like `try_mutate_into`, but leaving `self` as it is
+        let mut value = self.clone();
+        value.try_mutate_into(into_type)?;
+        Ok(value)
     }
 
     pub fn unary_pos(&self) -> Self {
@@ -265,111 +404,590 @@ impl Value {
     }
 
     pub fn unary_neg(&self) -> Self {
-        match self.type_ {
-            ValueType::Bitseq => Self::from(-self.val_bitseq),
-            ValueType::Decimal => Self::from(-self.val_decimal),
-            ValueType::Integer => Self::from(-self.val_integer),
+        match self.inner {
+            ValueInner::Bitseq(b) if self._is_fractional() => Self::from(-Decimal::from(b)),
+            ValueInner::Bitseq(b) => Self::from(-b),
+            ValueInner::Decimal(d) => Self::from(-d),
+            ValueInner::Integer(i) => Self::from(-i),
         }
     }
 
     pub fn logical_neg(&self) -> Self {
-        let is_zero = match self.type_ {
-            ValueType::Bitseq => self.val_bitseq.is_zero(),
-            ValueType::Decimal => self.val_decimal == Decimal::ZERO,
-            ValueType::Integer => self.val_integer == Integer::ZERO,
-        };
-        Self::from(Integer::from(is_zero))
+        Self::from(Integer::from(self.is_zero()))
     }
 
     pub fn bitwise_neg(&self) -> Result<Self, ConversionError> {
-        let mut result = self.clone();
-        if result.type_ != ValueType::Bitseq {
-            result.try_mutate_into(ValueType::Bitseq)?;
+        let mut bitseq = self._to_bitseq()?;
+        bitseq.neg_mut();
+        Ok(Self::from(bitseq))
+    }
+
+    pub fn signed(&self) -> Result<Self, ConversionError> {
+        Ok(Self::from(self._to_bitseq()?.as_signed_integer()))
+    }
+
+    pub fn concat(&self, other: &Self) -> Result<Self, ConversionError> {
+        Ok(Self::from(self._to_bitseq()?.concat(other._to_bitseq()?)?))
+    }
+
+    pub fn bit(&self, index: &Self) -> Result<Self, InvalidOperationError> {
+        let bitseq = match self._to_bitseq() {
+            Ok(b) => b,
+            Err(e) => return Err(InvalidOperationError::new(e.msg)),
+        };
+        let index: Integer = match index.clone().try_into() {
+            Ok(i) => i,
+            Err(e) => return Err(InvalidOperationError::new(e.msg)),
+        };
+        let n = match index.inner_value().to_usize() {
+            Ok(n) => n,
+            Err(_) => {
+                return Err(InvalidOperationError::new(format!(
+                    "Bit index {} must be a non-negative integer",
+                    index
+                )));
+            }
+        };
+        match bitseq.get_bit(n) {
+            Some(true) => Ok(Self::from(Bitseq::ONE)),
+            Some(false) => Ok(Self::from(Bitseq::ZERO)),
+            None => Err(InvalidOperationError::new(format!(
+                "Bit index {} is out of range for {}",
+                n, bitseq
+            ))),
         }
-        result.val_bitseq.neg_mut();
-        Ok(result)
     }
 
-    pub fn factorial(&self) -> Result<Self, InvalidOperationError> {
-        let mut result = if self.type_ == ValueType::Bitseq {
-            Self::from(Integer::from(self.val_bitseq))
+    fn _count_ones(&self) -> Result<u32, InvalidOperationError> {
+        match self.inner {
+            ValueInner::Bitseq(b) => Ok(b.popcount()),
+            ValueInner::Integer(i) => Ok(i.popcount()),
+            ValueInner::Decimal(d) => match Integer::try_from(d) {
+                Ok(i) => Ok(i.popcount()),
+                Err(e) => Err(InvalidOperationError::new(e.msg)),
+            },
+        }
+    }
+
+    pub fn popcount(&self) -> Result<Self, InvalidOperationError> {
+        Ok(Self::from(Integer::from(self._count_ones()?)))
+    }
+
+    pub fn parity(&self) -> Result<Self, InvalidOperationError> {
+        Ok(Self::from(Integer::from(self._count_ones()? % 2)))
+    }
+
+    pub fn clz(&self) -> Result<Self, ConversionError> {
+        Ok(Self::from(Integer::from(
+            self._to_bitseq()?.leading_zeros(),
+        )))
+    }
+
+    pub fn ctz(&self) -> Result<Self, ConversionError> {
+        Ok(Self::from(Integer::from(
+            self._to_bitseq()?.trailing_zeros(),
+        )))
+    }
+
+    fn _shift_amount(&self) -> Result<usize, InvalidOperationError> {
+        let amount: Integer = match self.clone().try_into() {
+            Ok(i) => i,
+            Err(e) => return Err(InvalidOperationError::new(e.msg)),
+        };
+        match amount.inner_value().to_usize() {
+            Ok(n) => Ok(n),
+            Err(_) => Err(InvalidOperationError::new(format!(
+                "Shift amount {} must be a non-negative integer",
+                amount
+            ))),
+        }
+    }
+
+    fn _shift_operand(&self) -> Result<Integer, InvalidOperationError> {
+        if let ValueInner::Bitseq(b) = self.inner
+            && self._is_fractional()
+        {
+            return Err(InvalidOperationError::new(format!(
+                "Cannot shift the fixed-point Bitseq {}",
+                b
+            )));
+        }
+        match self.clone().try_into() {
+            Ok(i) => Ok(i),
+            Err(e) => Err(InvalidOperationError::new(e.msg)),
+        }
+    }
+
+    pub fn shl(&self, amount: &Self) -> Result<Self, InvalidOperationError> {
+        // A Bitseq that would outgrow its backing type is promoted to an Integer
+        let n = amount._shift_amount()?;
+        if let ValueInner::Bitseq(b) = self.inner
+            && !self._is_fractional()
+            && let Some(bitseq) = b.checked_shl(n)
+        {
+            return Ok(Self::from(bitseq));
+        }
+        let operand = self._shift_operand()?;
+        match u32::try_from(n).ok().and_then(|n| operand.checked_shl(n)) {
+            Some(i) => Ok(Self::from(i)),
+            None => Err(InvalidOperationError::new(format!(
+                "Shifting {} left by {} exceeds size of Integer type",
+                operand, n
+            ))),
+        }
+    }
+
+    pub fn shr(&self, amount: &Self) -> Result<Self, InvalidOperationError> {
+        let n = amount._shift_amount()?;
+        if let ValueInner::Bitseq(b) = self.inner
+            && !self._is_fractional()
+        {
+            return Ok(Self::from(b.shr(n)));
+        }
+        let operand = self._shift_operand()?;
+        Ok(Self::from(
+            operand.shr(u32::try_from(n).unwrap_or(u32::MAX)),
+        ))
+    }
+
+    fn _is_fractional(&self) -> bool {
+        match self.inner {
+            ValueInner::Bitseq(b) => b.fraction_bits() > 0,
+            ValueInner::Decimal(_) => true,
+            ValueInner::Integer(_) => false,
+        }
+    }
+
+    fn _common_type(&self, other: &Self) -> ValueType {
+        // Arithmetic happens on Decimals if either side is one, on Integers otherwise
+        if self._is_fractional() || other._is_fractional() {
+            ValueType::Decimal
         } else {
-            self.clone()
+            ValueType::Integer
+        }
+    }
+
+    pub fn add(&self, other: &Self) -> Result<Self, ConversionError> {
+        Ok(match self._common_type(other) {
+            ValueType::Decimal => Self::from(self._to_decimal() + other._to_decimal()),
+            _ => Self::from(self._to_integer()? + other._to_integer()?),
+        })
+    }
+
+    pub fn sub(&self, other: &Self) -> Result<Self, ConversionError> {
+        Ok(match self._common_type(other) {
+            ValueType::Decimal => Self::from(self._to_decimal() - other._to_decimal()),
+            _ => Self::from(self._to_integer()? - other._to_integer()?),
+        })
+    }
+
+    pub fn mul(&self, other: &Self) -> Result<Self, ConversionError> {
+        Ok(match self._common_type(other) {
+            ValueType::Decimal => Self::from(self._to_decimal() * other._to_decimal()),
+            _ => Self::from(self._to_integer()? * other._to_integer()?),
+        })
+    }
+
+    pub fn div(&self, other: &Self) -> Result<Self, InvalidOperationError> {
+        // Division always yields a Decimal, so that 7 / 2 is 3.5 rather than 3
+        if other.is_zero() {
+            return Err(InvalidOperationError::new("Division by zero"));
+        }
+        Ok(Self::from(self._to_decimal() / other._to_decimal()))
+    }
+
+    pub fn bits(&self) -> Result<Self, InvalidOperationError> {
+        // The minimal width of the value, so a Bitseq's leading zeros do not count
+        let integer = match self._to_integer() {
+            Ok(i) => i,
+            Err(e) => return Err(InvalidOperationError::new(e.msg)),
         };
-        match result.type_ {
-            ValueType::Bitseq => unreachable!(),
-            ValueType::Decimal => {
-                result.val_decimal = (result.val_decimal + Decimal::ONE).gamma()?
+        Ok(Self::from(Integer::from(integer.bit_width()?)))
+    }
+
+    pub fn to_string_base(&self, base: &Self) -> Result<String, InvalidOperationError> {
+        // Bases 2, 8 and 16 are written with their numeral prefix so that they read back
+        // as the same value, other bases as bare digits in uppercase
+        let base_number: Option<Integer> = base.clone().try_into().ok();
+        let radix = base_number
+            .and_then(|number| (2..=36).find(|n| Integer::from(*n) == number))
+            .ok_or_else(|| {
+                InvalidOperationError::new(format!(
+                    "The base must be a whole number from 2 to 36, not {}",
+                    base.to_numeral_string()
+                ))
+            })?;
+        let integer = match self._to_integer() {
+            Ok(i) => i,
+            Err(e) => return Err(InvalidOperationError::new(e.msg)),
+        };
+        Ok(match radix {
+            2 => format!("{self:#b}"),
+            8 => format!("{self:#o}"),
+            16 => format!("{self:#x}"),
+            _ => integer.to_string_base(radix).to_uppercase(),
+        })
+    }
+
+    pub fn to_numeral_string_in_base(&self, radix: u32, precision: u32) -> String {
+        // As `to_string_base` for whole values, and with as many digits after the point as
+        // `precision` decimal digits make up in the base otherwise; values too large for an
+        // Integer keep their decimal numeral string
+        let whole = self.to_string_base(&Self::from(Integer::from(radix)));
+        if self.is_integer() {
+            return whole.unwrap_or_else(|_| self.to_numeral_string());
+        }
+        let decimal = self._to_decimal();
+        let Ok(integer_part) = Integer::try_from(decimal - decimal.fract()) else {
+            return self.to_numeral_string();
+        };
+        let prefix = match radix {
+            2 => "0b",
+            8 => "0o",
+            16 => "0x",
+            _ => "",
+        };
+        let digits_of = |integer: Integer| {
+            let digits = integer
+                .to_string_base(radix)
+                .trim_start_matches('-')
+                .to_string();
+            if prefix.is_empty() {
+                digits.to_uppercase()
+            } else {
+                digits
             }
-            ValueType::Integer => result.val_integer = result.val_integer.factorial()?,
+        };
+        let sign = if decimal < Decimal::ZERO { "-" } else { "" };
+        let integer_digits = digits_of(integer_part);
+        let significant = (precision as f64 / (radix as f64).log10()).ceil() as usize;
+        let mut fraction_digits = String::new();
+        let mut fraction = decimal.fract().abs();
+        let base = Decimal::from(radix as u128);
+        while fraction != Decimal::ZERO
+            && (fraction_digits.is_empty()
+                || fraction_digits.len() + integer_digits.len() < significant)
+        {
+            fraction = fraction * base;
+            let digit = fraction - fraction.fract();
+            fraction = fraction.fract();
+            fraction_digits.push_str(&digits_of(
+                Integer::try_from(digit).unwrap_or(Integer::ZERO),
+            ));
         }
-        Ok(result)
+        format!("{sign}{prefix}{integer_digits}.{fraction_digits}")
     }
 
-    pub fn abs(&self) -> Self {
-        let mut result = self.clone();
-        match result.type_ {
-            ValueType::Bitseq => {}
-            ValueType::Decimal => result.val_decimal = result.val_decimal.abs(),
-            ValueType::Integer => result.val_integer = result.val_integer.abs(),
+    pub fn isprime(&self) -> Result<Self, InvalidOperationError> {
+        // 1 for a prime and 0 otherwise, so that 0, 1 and negative values give 0
+        let integer = match self._to_integer() {
+            Ok(i) => i,
+            Err(e) => return Err(InvalidOperationError::new(e.msg)),
+        };
+        Ok(Self::from(Integer::from(integer.is_prime())))
+    }
+
+    pub fn factors(&self) -> Result<String, InvalidOperationError> {
+        // The prime factorization as text, such as "2^3 * 3^2 * 5" for 360, with a factor of -1
+        // for negative values
+        let integer = match self._to_integer() {
+            Ok(i) => i,
+            Err(e) => return Err(InvalidOperationError::new(e.msg)),
+        };
+        let mut terms: Vec<String> = Vec::new();
+        if integer < Integer::ZERO {
+            terms.push("-1".to_string());
         }
-        result
+        for (prime, exponent) in integer.prime_factors()? {
+            terms.push(match exponent {
+                1 => prime.to_string(),
+                _ => format!("{prime}^{exponent}"),
+            });
+        }
+        if terms.is_empty() {
+            terms.push("1".to_string());
+        }
+        Ok(terms.join(" * "))
     }
 
-    pub fn sin(&self, mode: AngleUnit) -> Result<Self, InvalidOperationError> {
-        let mut result = self.clone();
-        if result.type_ != ValueType::Decimal {
-            if let Err(e) = result.try_mutate_into(ValueType::Decimal) {
-                return Err(InvalidOperationError::new(e.msg));
-            }
+    pub fn sum(values: &[Self]) -> Result<Self, InvalidOperationError> {
+        // The aggregates below are only called with at least one value, as lists cannot be empty
+        let mut total = values[0].clone();
+        for value in &values[1..] {
+            total = match total.add(value) {
+                Ok(v) => v,
+                Err(e) => return Err(InvalidOperationError::new(e.msg)),
+            };
         }
-        if result.val_decimal < Decimal::ZERO {
+        Ok(total)
+    }
+
+    pub fn mean(values: &[Self]) -> Result<Self, InvalidOperationError> {
+        Self::sum(values)?.div(&Self::from(Integer::from(values.len() as u32)))
+    }
+
+    pub fn stdev(values: &[Self]) -> Result<Self, InvalidOperationError> {
+        // The sample standard deviation, dividing by one less than the number of values
+        if values.len() < 2 {
+            return Err(InvalidOperationError::new(
+                "The standard deviation needs at least two values",
+            ));
+        }
+        let mean = Self::mean(values)?._to_decimal();
+        let squares = values
+            .iter()
+            .map(|value| {
+                let deviation = value._to_decimal() - mean;
+                deviation * deviation
+            })
+            .fold(Decimal::ZERO, |total, square| total + square);
+        let count = Decimal::from(Integer::from(values.len() as u32 - 1));
+        Self::from(squares / count).sqrt()
+    }
+
+    pub fn minimum(values: &[Self]) -> Self {
+        // The smallest value as it was given, so that its type is kept
+        values
+            .iter()
+            .min_by(|a, b| a._to_decimal().cmp(&b._to_decimal()))
+            .unwrap()
+            .clone()
+    }
+
+    pub fn maximum(values: &[Self]) -> Self {
+        values
+            .iter()
+            .max_by(|a, b| a._to_decimal().cmp(&b._to_decimal()))
+            .unwrap()
+            .clone()
+    }
+
+    pub fn recip(&self) -> Result<Self, InvalidOperationError> {
+        // Always a Decimal, like division, since there is no exact fraction type
+        if self.is_zero() {
             return Err(InvalidOperationError::new(
-                "Sine not defined for negative inputs, input must be >= 0",
+                "The reciprocal of zero is undefined",
             ));
         }
-        result.val_decimal = result.val_decimal.sin(mode);
+        Ok(Self::from(Decimal::ONE / self._to_decimal()))
+    }
+
+    pub fn floor_div(&self, other: &Self) -> Result<Self, InvalidOperationError> {
+        // floor(a / b), always an Integer, so that a == (a // b) * b + a % b holds with `rem`
+        if other.is_zero() {
+            return Err(InvalidOperationError::new("Division by zero"));
+        }
+        let quotient = match self._common_type(other) {
+            ValueType::Decimal => {
+                Integer::try_from((self._to_decimal() / other._to_decimal()).floor())
+            }
+            _ => self
+                ._to_integer()
+                .and_then(|a| Ok(a.div_floor(&other._to_integer()?))),
+        };
+        match quotient {
+            Ok(quotient) => Ok(Self::from(quotient)),
+            Err(e) => Err(InvalidOperationError::new(e.msg)),
+        }
+    }
+
+    pub fn rem(&self, other: &Self) -> Result<Self, InvalidOperationError> {
+        // The remainder of floor division, which takes the sign of the divisor
+        if other.is_zero() {
+            return Err(InvalidOperationError::new("Division by zero"));
+        }
+        if self._common_type(other) == ValueType::Decimal {
+            let (a, b) = (self._to_decimal(), other._to_decimal());
+            return Ok(Self::from(a - b * (a / b).floor()));
+        }
+        match self
+            ._to_integer()
+            .and_then(|a| Ok(a.rem_floor(&other._to_integer()?)))
+        {
+            Ok(remainder) => Ok(Self::from(remainder)),
+            Err(e) => Err(InvalidOperationError::new(e.msg)),
+        }
+    }
+
+    pub fn pow(&self, other: &Self) -> Result<Self, InvalidOperationError> {
+        // Integral operands stay Integers as long as the exponent is non-negative
+        let integral =
+            self._common_type(other) == ValueType::Integer && other._to_decimal() >= Decimal::ZERO;
+        let mut result = Self::from(self._to_decimal().pow(other._to_decimal())?);
+        if integral {
+            // A result too large for an Integer simply stays a Decimal
+            let _ = result.try_mutate_into(ValueType::Integer);
+        } else if other._to_decimal() > Decimal::ZERO {
+            // x^(1/n) is the n-th root of x, and may be exact
+            let degree = (Decimal::ONE / other._to_decimal()).round();
+            if degree > Decimal::ONE
+                && Decimal::ONE / degree == other._to_decimal()
+                && let Some(root) = Self::_exact_root(self, degree, &result)
+            {
+                result = root;
+            }
+        }
         Ok(result)
     }
 
-    pub fn cos(&self) -> Self {
-        todo!()
+    pub fn root(&self, radicand: &Self) -> Result<Self, InvalidOperationError> {
+        // `n rt x` is the n-th root of x, an Integer whenever x is a perfect n-th power
+        let degree = self._to_decimal();
+        if degree == Decimal::ZERO {
+            return Err(InvalidOperationError::new("The zeroth root is undefined"));
+        }
+        let result = radicand.pow(&Self::from(Decimal::ONE / degree))?;
+        Ok(Self::_exact_root(radicand, degree, &result).unwrap_or(result))
     }
 
-    pub fn tan(&self) -> Self {
-        todo!()
+    fn _exact_root(radicand: &Self, degree: Decimal, approximation: &Self) -> Option<Self> {
+        // The Integer nearest to `approximation`, if its `degree`-th power is `radicand`
+        let radicand = Integer::try_from(radicand._to_decimal()).ok()?;
+        let degree = Integer::try_from(degree)
+            .ok()?
+            .inner_value()
+            .to_i64()
+            .ok()?;
+        let root = Integer::try_from(approximation._to_decimal().round()).ok()?;
+        (root.checked_pow(u32::try_from(degree).ok()?)? == radicand).then(|| Self::from(root))
     }
 
-    pub fn cot(&self) -> Self {
-        todo!()
+    pub fn beta(&self, other: &Self) -> Result<Self, InvalidOperationError> {
+        let y = other._to_decimal();
+        self._map_decimal(|x| x.beta(y))
     }
 
-    pub fn sec(&self) -> Self {
-        todo!()
+    pub fn hypot(&self, other: &Self) -> Result<Self, InvalidOperationError> {
+        let y = other._to_decimal();
+        self._map_decimal(|x| x.hypot(y))
     }
 
-    pub fn csc(&self) -> Self {
-        todo!()
+    pub fn factorial(&self) -> Result<Self, InvalidOperationError> {
+        match self.inner {
+            ValueInner::Bitseq(b) if self._is_fractional() => {
+                Ok(Self::from((Decimal::from(b) + Decimal::ONE).gamma()?))
+            }
+            ValueInner::Bitseq(b) => Ok(Self::from(Integer::from(b).factorial()?)),
+            ValueInner::Decimal(d) => Ok(Self::from((d + Decimal::ONE).gamma()?)),
+            ValueInner::Integer(i) => Ok(Self::from(i.factorial()?)),
+        }
     }
 
-    pub fn exp(&self) -> Self {
-        todo!()
+    pub fn abs(&self) -> Self {
+        match self.inner {
+            ValueInner::Bitseq(_) => self.clone(),
+            ValueInner::Decimal(d) => Self::from(d.abs()),
+            ValueInner::Integer(i) => Self::from(i.abs()),
+        }
     }
 
-    pub fn ln(&self) -> Self {
-        todo!()
+    pub fn frac(&self) -> Self {
+        // Fractional part with the sign of the operand, e.g. frac(-2.75) == -0.75
+        if self._is_fractional() {
+            Self::from(self._to_decimal().fract())
+        } else {
+            Self::from(Integer::from(0u32))
+        }
     }
 
-    pub fn log(&self) -> Self {
-        todo!()
+    fn _map_decimal<F>(&self, f: F) -> Result<Self, InvalidOperationError>
+    where
+        F: FnOnce(Decimal) -> Result<Decimal, InvalidOperationError>,
+    {
+        Ok(Self::from(f(self._to_decimal())?))
+    }
+
+    pub fn sin(&self, unit: AngleUnit) -> Result<Self, InvalidOperationError> {
+        self._map_decimal(|d| Ok(d.sin(unit)))
+    }
+
+    pub fn cos(&self, unit: AngleUnit) -> Result<Self, InvalidOperationError> {
+        self._map_decimal(|d| Ok(d.cos(unit)))
+    }
+
+    pub fn tan(&self, unit: AngleUnit) -> Result<Self, InvalidOperationError> {
+        self._map_decimal(|d| d.tan(unit))
+    }
+
+    pub fn cot(&self, unit: AngleUnit) -> Result<Self, InvalidOperationError> {
+        self._map_decimal(|d| d.cot(unit))
+    }
+
+    pub fn sec(&self, unit: AngleUnit) -> Result<Self, InvalidOperationError> {
+        self._map_decimal(|d| d.sec(unit))
+    }
+
+    pub fn csc(&self, unit: AngleUnit) -> Result<Self, InvalidOperationError> {
+        self._map_decimal(|d| d.csc(unit))
+    }
+
+    pub fn asin(&self, unit: AngleUnit) -> Result<Self, InvalidOperationError> {
+        self._map_decimal(|d| d.asin(unit))
+    }
+
+    pub fn acos(&self, unit: AngleUnit) -> Result<Self, InvalidOperationError> {
+        self._map_decimal(|d| d.acos(unit))
+    }
+
+    pub fn atan(&self, unit: AngleUnit) -> Result<Self, InvalidOperationError> {
+        self._map_decimal(|d| Ok(d.atan(unit)))
+    }
+
+    pub fn deg(&self) -> Result<Self, InvalidOperationError> {
+        // Radians to degrees, whatever the angle unit in use
+        self._map_decimal(|d| Ok(Decimal::from_radians_in_unit(d, AngleUnit::Degrees)))
+    }
+
+    pub fn rad(&self) -> Result<Self, InvalidOperationError> {
+        // Degrees to radians, whatever the angle unit in use
+        self._map_decimal(|d| Ok(d.to_radians_in_unit(AngleUnit::Degrees)))
+    }
+
+    pub fn asinh(&self) -> Result<Self, InvalidOperationError> {
+        self._map_decimal(Decimal::asinh)
+    }
+
+    pub fn acosh(&self) -> Result<Self, InvalidOperationError> {
+        self._map_decimal(Decimal::acosh)
+    }
+
+    pub fn atanh(&self) -> Result<Self, InvalidOperationError> {
+        self._map_decimal(Decimal::atanh)
+    }
+
+    pub fn exp(&self) -> Result<Self, InvalidOperationError> {
+        self._map_decimal(Decimal::exp)
+    }
+
+    pub fn ln(&self) -> Result<Self, InvalidOperationError> {
+        self._map_decimal(Decimal::ln)
+    }
+
+    pub fn log2(&self) -> Result<Self, InvalidOperationError> {
+        self._map_decimal(Decimal::log2)
+    }
+
+    pub fn log10(&self) -> Result<Self, InvalidOperationError> {
+        self._map_decimal(Decimal::log10)
+    }
+
+    pub fn log(&self) -> Result<Self, InvalidOperationError> {
+        // The common logarithm, as on most calculators
+        self.log10()
+    }
+
+    pub fn logb(&self, other: &Self) -> Result<Self, InvalidOperationError> {
+        // Logarithm of `other` to the base `self`, so that 2 logb 8 is 3
+        let base = self._to_decimal();
+        other._map_decimal(|d| d.log(base))
     }
 
     pub fn lg(&self) -> Self {
         todo!()
     }
 
-    pub fn sqrt(&self) -> Self {
-        todo!()
+    pub fn sqrt(&self) -> Result<Self, InvalidOperationError> {
+        self._map_decimal(Decimal::sqrt)
     }
 
     pub fn cbrt(&self) -> Self {
@@ -405,11 +1023,7 @@ impl TryFrom<&str> for Value {
 
 impl Into<Decimal> for Value {
     fn into(self) -> Decimal {
-        match self.type_ {
-            ValueType::Bitseq => self.val_bitseq.into(),
-            ValueType::Decimal => self.val_decimal,
-            ValueType::Integer => self.val_integer.into(),
-        }
+        self._to_decimal()
     }
 }
 
@@ -417,10 +1031,10 @@ impl TryInto<Integer> for Value {
     type Error = ConversionError;
 
     fn try_into(self) -> Result<Integer, Self::Error> {
-        match self.type_ {
-            ValueType::Bitseq => Ok(self.val_bitseq.into()),
-            ValueType::Integer => Ok(self.val_integer),
-            ValueType::Decimal => self.val_decimal.try_into(),
+        match self.inner {
+            ValueInner::Bitseq(b) => Ok(b.into()),
+            ValueInner::Integer(i) => Ok(i),
+            ValueInner::Decimal(d) => d.try_into(),
         }
     }
 }
@@ -429,30 +1043,157 @@ impl TryInto<Bitseq> for Value {
     type Error = ConversionError;
 
     fn try_into(self) -> Result<Bitseq, Self::Error> {
-        match self.type_ {
-            ValueType::Bitseq => Ok(self.val_bitseq),
-            ValueType::Integer => Bitseq::try_from(self.val_integer),
-            ValueType::Decimal => Bitseq::try_from(self.val_decimal),
+        self._to_bitseq()
+    }
+}
+
+impl FromStr for Value {
+    type Err = SyntaxError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let base: u8 = if let Some(b) = Self::_check_str_and_get_base(s) {
+            b
+        } else if let Some(e) = Self::_invalid_prefixed_digit_error(s) {
+            return Err(e);
+        } else if let Some(e) = Self::_separator_after_prefix_error(s) {
+            return Err(e);
+        } else {
+            return Err(SyntaxError::new(format!(
+                "The pattern of the numeral string \"{}\" is invalid",
+                s
+            )));
+        };
+        if patterns::DECIMAL_SCIENTIFIC.is_match(s) {
+            Self::_from_scientific_str(s)
+        } else if Self::_has_fractional_separator(s) && base == 2 {
+            Self::_from_fixed_point_bitseq_str(s)
+        } else if Self::_has_fractional_separator(s) {
+            Self::_from_dec_str(s, base)
+        } else if base == 2 {
+            Self::_from_bitseq_str(s)
+        } else {
+            Self::_from_int_str(s, base)
+        }
+    }
+}
+
+// The wire form of a Value: arbitrary-precision numbers travel as strings, so that no
+// format has to represent them natively
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type")]
+enum SerializedValue {
+    Bitseq {
+        value: String,
+        len: usize,
+        point: usize,
+    },
+    Decimal {
+        value: String,
+    },
+    Integer {
+        value: String,
+    },
+}
+
+impl Serialize for Value {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let serialized = match self.inner {
+            ValueInner::Bitseq(b) => SerializedValue::Bitseq {
+                value: b.inner_value().to_string(),
+                len: b.bit_length(),
+                point: b.fraction_bits(),
+            },
+            ValueInner::Decimal(d) => SerializedValue::Decimal {
+                value: d.inner_value().to_string(),
+            },
+            ValueInner::Integer(i) => SerializedValue::Integer {
+                value: i.to_string(),
+            },
+        };
+        serialized.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Value {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let invalid = |s: &str| D::Error::custom(format!("invalid value \"{s}\""));
+        match SerializedValue::deserialize(deserializer)? {
+            SerializedValue::Bitseq { value, len, point } => {
+                let bits = value.parse().map_err(|_| invalid(&value))?;
+                match Bitseq::from_parts(bits, len, point) {
+                    Some(b) => Ok(Self::from(b)),
+                    None => Err(invalid(&value)),
+                }
+            }
+            SerializedValue::Decimal { value } => match Decimal::from_str(&value) {
+                Ok(d) => Ok(Self::from(d)),
+                Err(_) => Err(invalid(&value)),
+            },
+            SerializedValue::Integer { value } => match Integer::from_str_radix(&value, 10) {
+                Ok(i) => Ok(Self::from(i)),
+                Err(_) => Err(invalid(&value)),
+            },
         }
     }
 }
 
+// The operators go through the same type promotion as the evaluator, and yield a Result as
+// the conversions involved can fail, so that `(&a + &b)?` works for library users. They are
+// only implemented on references, as by-value impls would shadow the methods of the same name
+macro_rules! impl_value_ops {
+    ( $($trait_:ident, $method:ident);*; ) => {
+        $(
+            impl $trait_ for &Value {
+                type Output = Result<Value, TCalcError>;
+
+                fn $method(self, rhs: Self) -> Self::Output {
+                    Value::$method(self, rhs).map_err(From::from)
+                }
+            }
+        )*
+    };
+}
+
+impl_value_ops! {
+    Add, add;
+    Sub, sub;
+    Mul, mul;
+    Div, div;
+}
+
+impl Binary for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self._fmt_in_base(f, 2, "0b")
+    }
+}
+
+impl Octal for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self._fmt_in_base(f, 8, "0o")
+    }
+}
+
+impl LowerHex for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self._fmt_in_base(f, 16, "0x")
+    }
+}
+
 impl Display for Value {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let vtype = match self.type_ {
-            ValueType::Bitseq => "Bitseq",
-            ValueType::Integer => "Integer",
-            ValueType::Decimal => "Decimal",
-        };
-        let val = match self.type_ {
-            ValueType::Bitseq => self.val_bitseq.to_string(),
-            ValueType::Integer => self.val_integer.to_string(),
-            ValueType::Decimal => self.val_decimal.to_string(),
-        };
-        write!(f, "Value({}: {})", vtype, val)
+        // The formatting options (width, fill, alignment, sign and a Decimal's precision)
+        // apply to the number itself rather than to the text around it
+        write!(f, "Value({}: ", self.value_type())?;
+        match &self.inner {
+            ValueInner::Bitseq(b) => Display::fmt(b, f)?,
+            ValueInner::Decimal(d) => Display::fmt(d, f)?,
+            ValueInner::Integer(i) => Display::fmt(i, f)?,
+        }
+        write!(f, ")")
     }
 }
 
+#[derive(Clone)]
 pub struct ValueStore {
     pub map: HashMap<String, Value>,
     _protected_keys: HashSet<String>,
@@ -505,10 +1246,29 @@ impl ValueStore {
         self.map.get(&identifier.as_ref().to_lowercase())
     }
 
+    pub fn remove<S: AsRef<str>>(&mut self, identifier: S) -> Option<Value> {
+        // Readonly entries can no more be removed than they can be overwritten
+        let identifier = identifier.as_ref().to_lowercase();
+        if self._readonly_keys.contains(&identifier) {
+            return None;
+        }
+        self.map.remove(&identifier)
+    }
+
     pub fn contains<S: AsRef<str>>(&self, identifier: S) -> bool {
         self.map.contains_key(&identifier.as_ref().to_lowercase())
     }
 
+    pub fn is_protected<S: AsRef<str>>(&self, identifier: S) -> bool {
+        self._protected_keys
+            .contains(&identifier.as_ref().to_lowercase())
+    }
+
+    pub fn is_readonly<S: AsRef<str>>(&self, identifier: S) -> bool {
+        self._readonly_keys
+            .contains(&identifier.as_ref().to_lowercase())
+    }
+
     pub fn clear(&mut self) {
         self.map.retain(|k, _| self._protected_keys.contains(k));
         self._readonly_keys
@@ -540,3 +1300,441 @@ impl From<HashMap<String, Value>> for ValueStore {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display_pads_and_aligns_the_number() {
+        let decimal = Value::from_str("1.5").unwrap();
+        assert_eq!(format!("{decimal:>8.2}"), "Value(Decimal:     1.50)");
+        assert_eq!(format!("{decimal:<8.2}"), "Value(Decimal: 1.50    )");
+        assert_eq!(format!("{decimal:*^8.2}"), "Value(Decimal: **1.50**)");
+        let integer = Value::from(Integer::from(-42i128));
+        assert_eq!(format!("{integer:>5}"), "Value(Integer:   -42)");
+        assert_eq!(format!("{integer:05}"), "Value(Integer: -0042)");
+        let bitseq = Value::from_str("0b101").unwrap();
+        assert_eq!(format!("{bitseq:<7}"), "Value(Bitseq: 0b101  )");
+    }
+
+    #[test]
+    fn display_honours_the_sign_flag() {
+        let decimal = Value::from_str("1.5").unwrap();
+        assert_eq!(format!("{decimal:+}"), "Value(Decimal: +1.5)");
+        assert_eq!(format!("{decimal:+.2}"), "Value(Decimal: +1.50)");
+        let integer = Value::from(Integer::from(42u32));
+        assert_eq!(format!("{integer:+}"), "Value(Integer: +42)");
+        let negative = Value::from(Integer::from(-42i128));
+        assert_eq!(format!("{negative:+}"), "Value(Integer: -42)");
+    }
+
+    #[test]
+    fn numerals_parse_into_values() {
+        for (numeral, expected) in [
+            ("3.14", "Value(Decimal: 3.14)"),
+            ("0x1F", "Value(Integer: 31)"),
+            ("0b101", "Value(Bitseq: 0b101)"),
+        ] {
+            assert_eq!(numeral.parse::<Value>().unwrap().to_string(), expected);
+        }
+        assert!("3.1.4".parse::<Value>().is_err());
+    }
+
+    #[test]
+    fn values_format_in_binary_octal_and_hex() {
+        let integer = Value::from(Integer::from(255u32));
+        assert_eq!(format!("{integer:x}"), "ff");
+        assert_eq!(format!("{integer:#x}"), "0xff");
+        assert_eq!(format!("{integer:o}"), "377");
+        assert_eq!(format!("{integer:b}"), "11111111");
+        let bitseq = Value::from_str("0b00101").unwrap();
+        assert_eq!(format!("{bitseq:b}"), "00101");
+        assert_eq!(format!("{bitseq:#b}"), "0b00101");
+        let negative = Value::from(Integer::from(-255i128));
+        assert_eq!(format!("{negative:#x}"), "-0xff");
+        let fraction = Value::from_str("2.5").unwrap();
+        assert_eq!(format!("{fraction:x}"), "2.5");
+    }
+
+    #[test]
+    fn values_round_trip_through_serde() {
+        for (value, json) in [
+            (
+                Value::from_str("0b00101").unwrap(),
+                r#"{"type":"Bitseq","value":"5","len":5,"point":0}"#,
+            ),
+            (
+                Value::from_str("0b10.1").unwrap(),
+                r#"{"type":"Bitseq","value":"5","len":3,"point":1}"#,
+            ),
+            (
+                Value::from(Decimal::PI),
+                &format!(
+                    r#"{{"type":"Decimal","value":"{}"}}"#,
+                    Decimal::PI.inner_value()
+                ),
+            ),
+            (
+                Value::from(Integer::from(-(1i128 << 100))),
+                r#"{"type":"Integer","value":"-1267650600228229401496703205376"}"#,
+            ),
+        ] {
+            assert_eq!(serde_json::to_string(&value).unwrap(), json);
+            let round_trip: Value = serde_json::from_str(json).unwrap();
+            assert_eq!(round_trip.value_type(), value.value_type());
+            assert_eq!(round_trip.to_string(), value.to_string());
+        }
+        assert!(serde_json::from_str::<Value>(r#"{"type":"Integer","value":"x"}"#).is_err());
+        assert!(
+            serde_json::from_str::<Value>(r#"{"type":"Bitseq","value":"5","len":2,"point":0}"#)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn base_prefixes_report_the_digit_that_does_not_fit() {
+        for (numeral, message) in [
+            (
+                "0b2",
+                "'2' is not a valid binary digit in the numeral \"0b2\"",
+            ),
+            (
+                "0o9",
+                "'9' is not a valid octal digit in the numeral \"0o9\"",
+            ),
+            (
+                "0xG",
+                "'G' is not a valid hexadecimal digit in the numeral \"0xG\"",
+            ),
+        ] {
+            assert_eq!(Value::from_str(numeral).err().unwrap().msg, message);
+        }
+    }
+
+    #[test]
+    fn numerals_may_start_with_the_fractional_separator() {
+        assert_eq!(
+            Value::from_str(".5").unwrap().to_string(),
+            "Value(Decimal: 0.5)"
+        );
+        assert_eq!(
+            Value::from_str(",25").unwrap().to_string(),
+            "Value(Decimal: 0.25)"
+        );
+        assert!(Value::from_str(".").is_err());
+        assert!(Value::from_str(",").is_err());
+    }
+
+    #[test]
+    fn numerals_may_end_with_the_fractional_separator() {
+        let five = Value::from_str("5.").unwrap();
+        assert_eq!(five.to_string(), "Value(Decimal: 5.0)");
+        assert!(five.is_integer());
+        assert_eq!(
+            Value::from_str("0x1F.").unwrap().to_string(),
+            "Value(Decimal: 31.0)"
+        );
+        assert!(Value::from_str("5.6.").is_err());
+    }
+
+    #[test]
+    fn digits_may_be_grouped_in_every_base() {
+        for (numeral, expected) in [
+            ("0b1_0", "Value(Bitseq: 0b10)"),
+            ("0o7_7_7", "Value(Integer: 511)"),
+            ("0d1_000", "Value(Integer: 1000)"),
+            ("1_000", "Value(Integer: 1000)"),
+            ("0xF_F", "Value(Integer: 255)"),
+        ] {
+            assert_eq!(Value::from_str(numeral).unwrap().to_string(), expected);
+        }
+        for numeral in ["0b_1", "0o_7", "0d_1", "0x_F"] {
+            assert!(Value::from_str(numeral).is_err(), "{numeral}");
+        }
+    }
+
+    #[test]
+    fn base_prefixes_are_stripped_before_separators_are_normalised() {
+        for (numeral, expected) in [
+            ("0d1,5", "Value(Decimal: 1.5)"),
+            ("0d1_0,5", "Value(Decimal: 10.5)"),
+            ("0x1_F", "Value(Integer: 31)"),
+            ("0x1_F.8", "Value(Decimal: 31.5)"),
+            ("0b1_0.1", "Value(Bitseq: 0b10.1)"),
+        ] {
+            assert_eq!(Value::from_str(numeral).unwrap().to_string(), expected);
+        }
+        assert_eq!(Value::_strip_str("0d1,5"), "1.5");
+        assert_eq!(Value::_strip_str("1_0,5"), "10.5");
+    }
+
+    #[test]
+    fn values_hold_only_their_active_representation() {
+        let representations = size_of::<Bitseq>() + size_of::<Decimal>() + size_of::<Integer>();
+        assert!(size_of::<Value>() < representations);
+        for value in ["0b101", "2.5", "42"] {
+            let value = Value::from_str(value).unwrap();
+            assert_eq!(value.clone().to_string(), value.to_string());
+        }
+    }
+
+    #[test]
+    fn parity_is_the_popcount_modulo_two() {
+        for (value, parity) in [("0b111", 1u32), ("0b11", 0), ("0b0", 0), ("7", 1), ("6", 0)] {
+            let value = Value::from_str(value).unwrap();
+            let parity = Value::from(Integer::from(parity));
+            assert_eq!(value.parity().unwrap().to_string(), parity.to_string());
+        }
+    }
+
+    #[test]
+    fn floor_division_rounds_down_and_pairs_with_modulo() {
+        let integer = |n: i128| Value::from(Integer::from(n));
+        let two = integer(2);
+        assert_eq!(
+            integer(7).floor_div(&two).unwrap().to_string(),
+            "Value(Integer: 3)"
+        );
+        assert_eq!(
+            integer(-7).floor_div(&two).unwrap().to_string(),
+            "Value(Integer: -4)"
+        );
+        for (a, b) in [
+            ("7", "2"),
+            ("-7", "2"),
+            ("7", "-2"),
+            ("-7", "-2"),
+            ("7.5", "2"),
+            ("-7.5", "-2"),
+        ] {
+            let value = |s: &str| match s.parse::<i128>() {
+                Ok(n) => integer(n),
+                Err(_) => Value::from(s.parse::<Decimal>().unwrap()),
+            };
+            let (a, b) = (value(a), value(b));
+            let quotient = a.floor_div(&b).unwrap();
+            assert!(quotient.is_integer());
+            let identity = quotient.mul(&b).unwrap().add(&a.rem(&b).unwrap()).unwrap();
+            assert_eq!(identity._to_decimal(), a._to_decimal(), "{a} // {b}");
+        }
+        assert!(integer(7).floor_div(&integer(0)).is_err());
+        assert!(integer(7).rem(&integer(0)).is_err());
+    }
+
+    #[test]
+    fn the_fractional_part_keeps_the_sign() {
+        for (value, frac) in [
+            ("2.75", "Value(Decimal: 0.75)"),
+            ("-2.75", "Value(Decimal: -0.75)"),
+            ("3.0", "Value(Decimal: 0.0)"),
+        ] {
+            let value = Value::from(value.parse::<Decimal>().unwrap());
+            assert_eq!(value.frac().to_string(), frac);
+        }
+        for value in ["3", "0b101"] {
+            let value = Value::from_str(value).unwrap();
+            assert_eq!(value.frac().to_string(), "Value(Integer: 0)");
+        }
+    }
+
+    #[test]
+    fn the_reciprocal_of_zero_is_an_error() {
+        let four = Value::from_str("4").unwrap();
+        assert_eq!(four.recip().unwrap().to_string(), "Value(Decimal: 0.25)");
+        let half = Value::from_str("0.5").unwrap();
+        assert_eq!(half.recip().unwrap().to_string(), "Value(Decimal: 2.0)");
+        assert!(Value::from_str("0").unwrap().recip().is_err());
+        assert!(Value::from_str("0.0").unwrap().recip().is_err());
+    }
+
+    #[test]
+    fn deg_and_rad_convert_whatever_the_angle_unit() {
+        let pi = Value::from(Decimal::PI);
+        assert_eq!(pi.deg().unwrap()._to_decimal(), Decimal::from(180u128));
+        let half_pi = Value::from(Decimal::PI / Decimal::from(2u128));
+        assert_eq!(half_pi.deg().unwrap()._to_decimal(), Decimal::from(90u128));
+        let straight = Value::from(Integer::from(180u32));
+        assert_eq!(straight.rad().unwrap()._to_decimal(), Decimal::PI);
+        assert_eq!(
+            straight.rad().unwrap().deg().unwrap()._to_decimal(),
+            Decimal::from(180u128)
+        );
+    }
+
+    #[test]
+    fn exact_roots_are_integers() {
+        let integer = |n: u32| Value::from(Integer::from(n));
+        for (degree, radicand, root) in [(2, 16, 4), (3, 27, 3), (5, 1 << 30, 64)] {
+            let result = integer(degree).root(&integer(radicand)).unwrap();
+            assert_eq!(result.to_string(), integer(root).to_string());
+        }
+        let third = Value::from(Decimal::ONE / Decimal::from(3u128));
+        assert_eq!(
+            integer(8).pow(&third).unwrap().to_string(),
+            "Value(Integer: 2)"
+        );
+        // Roots that are not whole stay Decimals
+        let root = integer(2).root(&integer(15)).unwrap();
+        assert_eq!(root.value_type(), ValueType::Decimal);
+        assert!(integer(0).root(&integer(16)).is_err());
+    }
+
+    #[test]
+    fn hex_numerals_are_bitseqs_four_bits_per_digit() {
+        for (numeral, bits) in [
+            ("0x0F", "0b00001111"),
+            ("0xFF", "0b11111111"),
+            ("0x00", "0b00000000"),
+        ] {
+            let value = Value::bitseq_from_str(numeral).unwrap();
+            assert_eq!(value.to_string(), format!("Value(Bitseq: {bits})"));
+            let ValueInner::Bitseq(b) = value.inner else {
+                unreachable!()
+            };
+            assert_eq!(b.bit_length(), 8);
+        }
+        // Read as numbers, hex numerals are still Integers
+        assert_eq!(
+            Value::from_str("0x0F").unwrap().to_string(),
+            "Value(Integer: 15)"
+        );
+        assert!(Value::bitseq_from_str("0x0F.8").is_err());
+    }
+
+    #[test]
+    fn bit_widths_grow_past_powers_of_two() {
+        for (value, width) in [
+            ("0", 1u32),
+            ("1", 1),
+            ("2", 2),
+            ("255", 8),
+            ("256", 9),
+            ("65535", 16),
+            ("65536", 17),
+            ("0b0001", 1),
+        ] {
+            let value = Value::from_str(value).unwrap();
+            let width = Value::from(Integer::from(width));
+            assert_eq!(value.bits().unwrap().to_string(), width.to_string());
+        }
+        assert!(Value::from(Integer::from(-1i128)).bits().is_err());
+        assert!(Value::from_str("2.5").unwrap().bits().is_err());
+    }
+
+    #[test]
+    fn casts_fail_rather_than_lose_information() {
+        let whole = Value::from_str("3.0").unwrap();
+        assert_eq!(
+            whole.cast(ValueType::Integer).unwrap().to_string(),
+            "Value(Integer: 3)"
+        );
+        assert_eq!(whole.to_string(), "Value(Decimal: 3.0)");
+        let ten = Value::from_str("10").unwrap();
+        assert_eq!(
+            ten.cast(ValueType::Bitseq).unwrap().to_string(),
+            "Value(Bitseq: 0b1010)"
+        );
+        assert_eq!(
+            ten.cast(ValueType::Decimal).unwrap().to_string(),
+            "Value(Decimal: 10.0)"
+        );
+        let bitseq = Value::from_str("0b101").unwrap();
+        assert_eq!(
+            bitseq.cast(ValueType::Integer).unwrap().to_string(),
+            "Value(Integer: 5)"
+        );
+        let fraction = Value::from_str("3.5").unwrap();
+        assert!(fraction.cast(ValueType::Integer).is_err());
+        assert!(fraction.cast(ValueType::Bitseq).is_err());
+        assert!(
+            Value::from(Integer::from(-1i128))
+                .cast(ValueType::Bitseq)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn isprime_is_one_or_zero() {
+        for (value, prime) in [
+            ("97", 1u32),
+            ("91", 0),
+            ("0", 0),
+            ("1", 0),
+            ("2", 1),
+            ("0b111", 1),
+        ] {
+            let value = Value::from_str(value).unwrap();
+            let prime = Value::from(Integer::from(prime));
+            assert_eq!(value.isprime().unwrap().to_string(), prime.to_string());
+        }
+        assert!(Value::from_str("7.5").unwrap().isprime().is_err());
+    }
+
+    #[test]
+    fn arithmetic_operators_promote_across_types() {
+        let bitseq = Value::from_str("0b101").unwrap();
+        let integer = Value::from_str("2").unwrap();
+        let decimal = Value::from_str("2.5").unwrap();
+        for (result, expected) in [
+            (&bitseq + &integer, "Value(Integer: 7)"),
+            (&integer - &bitseq, "Value(Integer: -3)"),
+            (&bitseq * &decimal, "Value(Decimal: 12.5)"),
+            (&decimal * &integer, "Value(Decimal: 5.0)"),
+            (&bitseq / &integer, "Value(Decimal: 2.5)"),
+            (&integer / &decimal, "Value(Decimal: 0.8)"),
+            (&decimal - &decimal, "Value(Decimal: 0.0)"),
+        ] {
+            assert_eq!(result.unwrap().to_string(), expected);
+        }
+        let zero = Value::from(Integer::ZERO);
+        assert!((&integer / &zero).is_err());
+        // Bitseqs with a fractional part are added as Decimals
+        let fixed_point = Value::from_str("0b10.1").unwrap();
+        assert_eq!(
+            (&fixed_point + &integer).unwrap().to_string(),
+            "Value(Decimal: 4.5)"
+        );
+    }
+
+    #[test]
+    fn predicates_hold_across_types() {
+        for (numeral, value_type, is_zero, is_integer) in [
+            ("0b000", ValueType::Bitseq, true, true),
+            ("0b101", ValueType::Bitseq, false, true),
+            ("0b11.0", ValueType::Bitseq, false, true),
+            ("0b0.1", ValueType::Bitseq, false, false),
+            ("0.0", ValueType::Decimal, true, true),
+            ("3.0", ValueType::Decimal, false, true),
+            ("3.5", ValueType::Decimal, false, false),
+            ("0", ValueType::Integer, true, true),
+            ("42", ValueType::Integer, false, true),
+        ] {
+            let value = Value::from_str(numeral).unwrap();
+            assert_eq!(value.value_type(), value_type, "{numeral}");
+            assert_eq!(value.is_zero(), is_zero, "{numeral}");
+            assert_eq!(value.is_integer(), is_integer, "{numeral}");
+        }
+    }
+
+    // A benchmark rather than a test: cargo test --release -- --ignored --nocapture
+    #[test]
+    #[ignore]
+    fn benchmark_cloning_values() {
+        let values: Vec<Value> = (0..100_000u32)
+            .map(|n| match n % 2 {
+                0 => Value::from(Decimal::PI),
+                _ => Value::from(Integer::from(n)),
+            })
+            .collect();
+        let start = std::time::Instant::now();
+        for _ in 0..10 {
+            std::hint::black_box(values.clone());
+        }
+        println!(
+            "{} bytes per value, 1M clones: {:?}",
+            size_of::<Value>(),
+            start.elapsed()
+        );
+    }
+}
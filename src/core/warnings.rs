@@ -0,0 +1,30 @@
+use std::fmt::Display;
+
+use crate::core::errors::InputPosition;
+
+// Reported alongside a result rather than instead of it, so evaluation carries on
+#[derive(Debug, Clone)]
+pub struct Warning {
+    pub msg: String,
+    pub position: InputPosition,
+}
+
+impl Warning {
+    pub fn newp<S: AsRef<str>>(msg: S, position: InputPosition) -> Self {
+        Self {
+            msg: msg.as_ref().to_string(),
+            position,
+        }
+    }
+}
+
+impl Display for Warning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Warning: {} at {}",
+            self.msg,
+            self.position.display_one_based()
+        )
+    }
+}
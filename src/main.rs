@@ -31,10 +31,12 @@ fn main() {
     //                                                                 28.1                    3
     //let input = "+ ~ sqrt ¬ + -test! \\blah foo := 0b0010010 - 2.55 0D587 0b010.01 (2 * 7)";
     let mut parser = Parser::new();
-    let mut evaluator = Evaluator::new();
+    let mut evaluator = Evaluator::new_with_debug_vars();
     let input = "pi!";
     println!("INPUT: {}", input);
-    let mut ast = parser.parse(input, 0, 0).unwrap();
+    let mut ast = parser
+        .parse(input, 0, 0, &evaluator.environment.functions)
+        .unwrap();
     evaluator.evaluate(&mut ast).unwrap();
     println!("===== Abstract Syntax Tree =====");
     println!("{}", ast);